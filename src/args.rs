@@ -0,0 +1,136 @@
+use hq;
+
+def_error! {
+    UnknownOption: "unknown option.",
+    InvalidPosition: "invalid position; expected +LINE or +LINE:COL.",
+}
+
+pub const USAGE: &'static str = "\
+Usage: thief [OPTION]... [+LINE[:COL]] [FILE]...
+
+Options:
+  -R, --read-only  open every buffer as read-only
+  -                read a buffer from the standard input
+  -h, --help       display this help and exit
+  -v, --version    output version information and exit
+
++LINE[:COL] moves the cursor of the following file. (1-based)
+";
+
+/// A file given from the command line.
+#[derive(Debug, PartialEq)]
+pub struct File {
+    pub path: String,
+    pub cursor: Option<hq::Pair>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub files: Vec<File>,
+    pub read_only: bool,
+    pub stdin: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Run(Args),
+    Help,
+    Version,
+}
+
+/// Parse `LINE[:COL]` into the 0-based cursor.
+pub fn parse_position(s: &str) -> Result<hq::Pair> {
+    let mut it = s.splitn(2, ':');
+    let line = it.next()
+        .and_then(|l| l.parse::<usize>().ok())
+        .ok_or(Error::InvalidPosition)?;
+    let col = match it.next() {
+        Some(c) => c.parse::<usize>().map_err(|_| Error::InvalidPosition)?,
+        None => 1,
+    };
+    Ok((col.saturating_sub(1), line.saturating_sub(1)))
+}
+
+/// Parse the arguments, excluding the program name.
+pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Action> {
+    let mut res: Args = Default::default();
+    let mut cursor = None;
+    let mut options = true;
+    for arg in args {
+        if options && arg.starts_with('+') && arg.len() > 1 {
+            cursor = Some(parse_position(&arg[1..])?);
+            continue;
+        }
+        match arg.as_str() {
+            "-h" | "--help" if options => return Ok(Action::Help),
+            "-v" | "--version" if options => return Ok(Action::Version),
+            "-R" | "--read-only" if options => res.read_only = true,
+            "-" if options => res.stdin = true,
+            "--" if options => options = false,
+            s if options && s.starts_with('-') => return Err(Error::UnknownOption),
+            _ => {
+                res.files.push(File {
+                    path: arg,
+                    cursor: cursor.take(),
+                })
+            }
+        }
+    }
+    // A trailing position belongs to the last file.
+    if let Some(c) = cursor {
+        if let Some(file) = res.files.last_mut() {
+            file.cursor = Some(c);
+        }
+    }
+    Ok(Action::Run(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_strs(args: &[&str]) -> Result<Action> {
+        parse(args.iter().map(|s| String::from(*s)))
+    }
+
+    #[test]
+    fn test_parse_files() {
+        if let Ok(Action::Run(args)) = parse_strs(&["a.rs", "+42:7", "b.rs", "+3"]) {
+            assert_eq!(
+                args.files,
+                vec![
+                    File {
+                        path: String::from("a.rs"),
+                        cursor: None,
+                    },
+                    File {
+                        path: String::from("b.rs"),
+                        cursor: Some((0, 2)),
+                    },
+                ]
+            );
+            assert!(!args.read_only && !args.stdin);
+        } else {
+            panic!("failed")
+        }
+        if let Ok(Action::Run(args)) = parse_strs(&["+42:7", "a.rs"]) {
+            assert_eq!(args.files[0].cursor, Some((6, 41)));
+        } else {
+            panic!("failed")
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(parse_strs(&["a.rs", "--help"]).unwrap(), Action::Help);
+        assert_eq!(parse_strs(&["-v"]).unwrap(), Action::Version);
+        assert!(parse_strs(&["-x"]).is_err());
+        assert!(parse_strs(&["+a:1", "a.rs"]).is_err());
+        if let Ok(Action::Run(args)) = parse_strs(&["-R", "-", "--", "-R"]) {
+            assert!(args.read_only && args.stdin);
+            assert_eq!(args.files[0].path, "-R");
+        } else {
+            panic!("failed")
+        }
+    }
+}
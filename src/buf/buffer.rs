@@ -1,19 +1,99 @@
-use std::io::BufRead;
+use std::cmp;
+use std::fs;
+use std::io::{Read, Write};
+use std::mem;
 use std::path;
 
 use buf;
 use hq;
-use util::ResultBox;
+use util::{self, ResultBox};
 use term;
 
+/// Lines scanned at most for the pair of a bracket.
+const PAIR_SCAN_LINES: usize = 5000;
+
+def_error! {
+    NoPath: "the buffer is not visiting a file.",
+}
+
 pub struct Buffer {
     cur: buf::Line,
     x: usize,
     prevs: Vec<term::String>,
     nexts: Vec<term::String>,
+    read_only: bool,
+    modified: bool,
+    auto_saved: bool,
+    format: buf::Format,
+    path: Option<path::PathBuf>,
+    stamp: Option<buf::Stamp>,
+    highlights: Vec<buf::Range>,
+    undos: Vec<Undo>,
+    word_chars: String,
+    tab_width: usize,
+    indent_tabs: bool,
+    indent_rules: buf::IndentRules,
+    auto_indent: bool,
+    comment: Option<buf::CommentMarkers>,
+    mark: Option<hq::Pair>,
+    /// The name of the major mode.
+    mode: String,
+    /// The name of the syntax, which gives the default settings.
+    syntax: String,
+    /// True iff the file is indented with tabs when loaded.
+    tabbed: bool,
+    /// The bracket highlights and the cursor where they are found.
+    brackets: Option<(hq::Pair, Vec<buf::Range>)>,
+}
+
+/// An edit recorded to be undone.
+enum Change {
+    /// The range of the inserted text.
+    Insert(buf::Range),
+    /// The text deleted at the position.
+    Delete(hq::Pair, String),
+}
+
+/// The edits since a checkpoint, undone at once, and the cursor to restore.
+struct Undo {
+    cursor: hq::Pair,
+    changes: Vec<Change>,
+}
+
+/// The position after the text inserted at `(x, y)`.
+fn end_of((x, y): hq::Pair, text: &str) -> hq::Pair {
+    match text.rfind('\n') {
+        Some(i) => (text[i + 1..].chars().count(), y + text.matches('\n').count()),
+        None => (x + text.chars().count(), y),
+    }
+}
+
+/// Scan the characters for `pair` of the bracket `c`, skipping literals and nested pairs.
+/// Return the index of the pair.
+fn scan_pair<'a, I: Iterator<Item = (usize, &'a term::Char)>>(
+    chars: I,
+    c: char,
+    pair: char,
+    depth: &mut usize,
+) -> Option<usize> {
+    for (i, chr) in chars {
+        if chr.literal {
+            continue;
+        }
+        if chr.chr == c {
+            *depth += 1;
+        } else if chr.chr == pair {
+            if *depth == 0 {
+                return Some(i);
+            }
+            *depth -= 1;
+        }
+    }
+    None
 }
 
 const BUFSIZE: usize = 80;
+const UNDO_LIMIT: usize = 100;
 
 impl Default for Buffer {
     fn default() -> Buffer {
@@ -22,6 +102,25 @@ impl Default for Buffer {
             x: Default::default(),
             prevs: Vec::with_capacity(BUFSIZE),
             nexts: Vec::with_capacity(BUFSIZE),
+            read_only: false,
+            modified: false,
+            auto_saved: false,
+            format: Default::default(),
+            path: None,
+            stamp: None,
+            highlights: vec![],
+            undos: vec![],
+            word_chars: String::new(),
+            tab_width: util::DEFAULT_TAB_WIDTH,
+            indent_tabs: false,
+            indent_rules: Default::default(),
+            auto_indent: true,
+            comment: None,
+            mark: None,
+            mode: String::new(),
+            syntax: String::new(),
+            tabbed: false,
+            brackets: None,
         }
     }
 }
@@ -79,37 +178,272 @@ impl Buffer {
         (self.x(), self.y())
     }
 
+    /// True iff the buffer must not be modified.
+    #[inline]
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Set the read-only flag.
+    #[inline]
+    pub fn set_read_only(&mut self, value: bool) {
+        self.read_only = value;
+    }
+
+    /// Construct a buffer from the highlighted lines.
+    fn from_lines(mut prevs: Vec<term::String>) -> Buffer {
+        let cur = buf::Line::new_from_string(prevs.pop().unwrap_or_else(term::String::new));
+        let mut buf = Buffer {
+            prevs: prevs,
+            cur: cur,
+            ..Default::default()
+        };
+        buf.set_cursor(0, 0);
+        buf
+    }
+
     /// Construct a buffer from a file.
     pub fn from_file<S: AsRef<path::Path> + ?Sized>(s: &S) -> ResultBox<Buffer> {
-        use syntect::easy::HighlightFile;
+        Buffer::load(s.as_ref(), s.as_ref())
+    }
+
+    /// Construct a buffer from a file, highlighted as the file in `syntax_path`,
+    /// e.g. the recovery file as its original.
+    fn load(s: &path::Path, syntax_path: &path::Path) -> ResultBox<Buffer> {
         use syntect::parsing::SyntaxSet;
-        use syntect::highlighting::{ThemeSet, Style};
+        use syntect::highlighting::ThemeSet;
 
         let ss = SyntaxSet::load_defaults_newlines();
         let ts = ThemeSet::load_defaults();
 
-        let mut highlighter = HighlightFile::new(s, &ss, &ts.themes["base16-ocean.dark"])?;
-        let mut prevs = vec![];
-        for line in highlighter.reader.lines() {
-            if let Ok(s) = line {
-                let regions: Vec<(Style, &str)> = highlighter.highlight_lines.highlight(&s);
-                let mut term_str = term::String::new();
-                for &(style, token) in regions.iter() {
-                    let mut colored =
-                        term::String::from_std(token, term::Brush::from_syntect(style));
-                    term_str.push_string(&mut colored);
+        let mut bytes = vec![];
+        fs::File::open(s)?.read_to_end(&mut bytes)?;
+        let (lines, format) = buf::Format::decode(&bytes);
+        // The original of a recovery file may not exist.
+        let syntax = ss.find_syntax_for_file(syntax_path)
+            .ok()
+            .and_then(|syntax| syntax)
+            .unwrap_or_else(|| ss.find_syntax_plain_text());
+        let mut highlighter = buf::LineHighlighter::new(syntax, &ts.themes["base16-ocean.dark"]);
+        let prevs = lines.iter().map(|line| highlighter.highlight(line)).collect();
+        let mut buf = Buffer::from_lines(prevs);
+        buf.format = format;
+        buf.read_only = fs::metadata(s)?.permissions().readonly();
+        buf.path = Some(s.to_path_buf());
+        buf.stamp = buf::Stamp::from_file(s).ok();
+        buf.syntax = syntax.name.clone();
+        buf.tabbed = lines.iter().any(|line| line.starts_with('\t'));
+        buf.reset_settings();
+        Ok(buf)
+    }
+
+    /// True iff the buffer has been changed since loaded or saved.
+    #[inline]
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Return every line as a plain string.
+    pub fn lines(&mut self) -> Vec<String> {
+        let mut res: Vec<String> = self.prevs.iter().map(|s| s.to_str()).collect();
+        res.push(self.cur.as_string().to_str());
+        res.extend(self.nexts.iter().rev().map(|s| s.to_str()));
+        res
+    }
+
+    /// Mark the buffer as modified.
+    #[inline]
+    fn touch(&mut self) {
+        self.modified = true;
+        self.auto_saved = false;
+        // The ranges are stale after the modification.
+        self.highlights.clear();
+        self.brackets = None;
+    }
+
+    /// Write every line into the given path, in the format of the buffer.
+    fn write_to(&mut self, path: &path::Path) -> ResultBox<()> {
+        let lines = self.lines();
+        let bytes = self.format.encode(&lines)?;
+        fs::File::create(path)?.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// The encoding and the line ending of the file.
+    #[inline]
+    pub fn format(&self) -> buf::Format {
+        self.format
+    }
+
+    /// Convert the encoding or the line ending. It is applied on save.
+    pub fn set_format(&mut self, format: buf::Format) -> ResultBox<()> {
+        format.encode(&self.lines())?;
+        self.format = format;
+        self.touch();
+        Ok(())
+    }
+
+    /// Write the buffer into its file.
+    pub fn save(&mut self) -> ResultBox<()> {
+        let path = self.path.clone().ok_or(Error::NoPath)?;
+        self.write_to(&path)?;
+        self.stamp = buf::Stamp::from_file(&path).ok();
+        self.modified = false;
+        // The recovery file is obsolete now.
+        self.remove_recovery()
+    }
+
+    /// Delete the recovery file if any.
+    fn remove_recovery(&self) -> ResultBox<()> {
+        if let Some(recovery) = self.recovery_path() {
+            if recovery.exists() {
+                fs::remove_file(recovery)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The recovery file of the buffer: `#name#` in the same directory.
+    pub fn recovery_path(&self) -> Option<path::PathBuf> {
+        self.path.as_ref().and_then(|path| {
+            path.file_name().and_then(|s| s.to_str()).map(|name| {
+                path.with_file_name(format!("#{}#", name))
+            })
+        })
+    }
+
+    /// Write the modified buffer into the recovery file.
+    /// Return true iff the recovery file has been written.
+    pub fn auto_save(&mut self) -> ResultBox<bool> {
+        if !self.modified || self.auto_saved {
+            return Ok(false);
+        }
+        let recovery = self.recovery_path().ok_or(Error::NoPath)?;
+        self.write_to(&recovery)?;
+        self.auto_saved = true;
+        Ok(true)
+    }
+
+    /// True iff there is a recovery file newer than the file.
+    pub fn has_recovery(&self) -> bool {
+        let modified = |p: &path::Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        match (self.path.as_ref(), self.recovery_path()) {
+            (Some(path), Some(recovery)) => {
+                match (modified(path), modified(&recovery)) {
+                    (Some(t), Some(t_recovery)) => t_recovery > t,
+                    (None, Some(_)) => true,
+                    _ => false,
                 }
-                prevs.push(term_str);
             }
+            _ => false,
         }
-        let cur = buf::Line::new_from_string(prevs.pop().unwrap());
-        let mut buf = Buffer {
-            prevs: prevs,
-            cur: cur,
+    }
+
+    /// Replace the content with the recovery file, keeping the cursor.
+    pub fn recover(&mut self) -> ResultBox<()> {
+        let recovery = self.recovery_path().ok_or(Error::NoPath)?;
+        let path = self.path.clone().ok_or(Error::NoPath)?;
+        let (x, y) = self.index_cursor();
+        let mut buf = Buffer::load(&recovery, &path)?;
+        self.keep_settings(&mut buf);
+        // The recovery file is written in the format of the buffer, not of the file,
+        // and its permissions are not the file's.
+        buf.read_only = self.read_only;
+        buf.format = self.format;
+        buf.set_cursor(x, y);
+        buf.path = self.path.take();
+        buf.stamp = self.stamp.take();
+        buf.modified = true;
+        buf.auto_saved = true;
+        *self = buf;
+        Ok(())
+    }
+
+    /// Reload the buffer from its file, keeping the cursor.
+    /// The changes are discarded, and so is the recovery file.
+    pub fn revert(&mut self) -> ResultBox<()> {
+        let path = self.path.clone().ok_or(Error::NoPath)?;
+        let (x, y) = self.index_cursor();
+        let mut buf = Buffer::from_file(&path)?;
+        self.keep_settings(&mut buf);
+        buf.set_cursor(x, y);
+        *self = buf;
+        self.remove_recovery()
+    }
+
+    /// Give the mode and its settings to the buffer reloaded,
+    /// which keeps the format and the permissions detected from the file.
+    fn keep_settings(&self, buf: &mut Buffer) {
+        buf.set_tab_width(self.tab_width);
+        buf.word_chars = self.word_chars.clone();
+        buf.indent_tabs = self.indent_tabs;
+        buf.indent_rules = self.indent_rules;
+        buf.comment = self.comment;
+        buf.mode = self.mode.clone();
+    }
+
+    /// Restore the settings given by the syntax or detected from the file.
+    pub fn reset_settings(&mut self) {
+        self.set_tab_width(util::DEFAULT_TAB_WIDTH);
+        self.word_chars = String::from(buf::word::word_chars(&self.syntax));
+        self.indent_tabs = self.tabbed;
+        self.indent_rules = buf::IndentRules::from_syntax(&self.syntax);
+        self.comment = buf::CommentMarkers::from_syntax(&self.syntax);
+    }
+
+    /// Return true iff the file has been changed on the disk since loaded or saved.
+    /// Each change is reported only once.
+    pub fn check_file(&mut self) -> bool {
+        match (self.path.as_ref(), self.stamp.as_mut()) {
+            (Some(path), Some(stamp)) => stamp.update(path).unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Construct an empty buffer visiting a file which does not exist yet.
+    /// The file is created on the first save.
+    pub fn new_file<S: AsRef<path::Path> + ?Sized>(s: &S) -> Buffer {
+        Buffer {
+            path: Some(s.as_ref().to_path_buf()),
             ..Default::default()
-        };
-        buf.set_cursor(0, 0);
-        Ok(buf)
+        }
+    }
+
+    /// True iff the buffer is visiting a file which does not exist yet.
+    pub fn is_new_file(&self) -> bool {
+        self.path.as_ref().map_or(false, |p| !p.exists())
+    }
+
+    /// True iff the directory to save the file exists.
+    pub fn parent_exists(&self) -> bool {
+        match self.path.as_ref().and_then(|p| p.parent()) {
+            Some(parent) => parent.as_os_str().is_empty() || parent.is_dir(),
+            None => true,
+        }
+    }
+
+    /// Create the missing directories to save the file.
+    pub fn create_parent(&self) -> ResultBox<()> {
+        if let Some(parent) = self.path.as_ref().and_then(|p| p.parent()) {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    /// Construct a plain text buffer from a string, e.g. the content of STDIN.
+    pub fn from_string(s: &str) -> Buffer {
+        use syntect::parsing::SyntaxSet;
+        use syntect::highlighting::ThemeSet;
+
+        let ss = SyntaxSet::load_defaults_newlines();
+        let ts = ThemeSet::load_defaults();
+
+        let mut highlighter = buf::LineHighlighter::new(
+            ss.find_syntax_plain_text(),
+            &ts.themes["base16-ocean.dark"],
+        );
+        let prevs = s.lines().map(|line| highlighter.highlight(line)).collect();
+        Buffer::from_lines(prevs)
     }
 
     /// Move up the cursor.
@@ -145,8 +479,11 @@ impl Buffer {
     /// Break the line at the location of the cursor.
     #[inline]
     pub fn break_line(&mut self) -> hq::Pair {
+        let start = self.index_cursor();
+        self.touch();
         self.prevs.push(self.cur.break_line());
         self.x = 0;
+        self.record_insert(start, "\n");
         self.cursor()
     }
 
@@ -155,32 +492,86 @@ impl Buffer {
         while self.prevs.len() > y {
             self.move_up(x);
         }
-        while self.prevs.len() < y && !self.prevs.is_empty() {
+        while self.prevs.len() < y && !self.nexts.is_empty() {
             self.move_down(x);
         }
         self.cur.set_cursor(x);
+        self.x = self.cur.x();
+    }
+
+    /// Move to the line, keeping the column.
+    pub fn move_to_line(&mut self, y: usize) -> hq::Pair {
+        let x = self.x;
+        while self.prevs.len() > y {
+            self.move_up(x);
+        }
+        while self.prevs.len() < y && !self.nexts.is_empty() {
+            self.move_down(x);
+        }
+        self.cursor()
+    }
+
+    /// Move to the beginning of the buffer.
+    pub fn move_begin_of_buffer(&mut self) -> hq::Pair {
+        self.set_cursor(0, 0);
+        self.cursor()
+    }
+
+    /// Move to the end of the buffer.
+    pub fn move_end_of_buffer(&mut self) -> hq::Pair {
+        let y = self.line_num() - 1;
+        self.set_cursor(usize::max_value(), y);
+        self.cursor()
     }
 
     /// Delete every characters after cursor.
     #[inline]
     pub fn kill_line(&mut self) -> KillLineRes {
-        if self.cur.kill() {
+        let start = self.index_cursor();
+        if let Some(text) = self.cur.kill() {
+            self.touch();
+            self.record_delete(start, &text);
             KillLineRes::Normal
         } else if let Some(line) = self.nexts.pop() {
+            self.touch();
             self.cur.append(line);
+            self.record_delete(start, "\n");
             KillLineRes::PullUp
         } else {
             KillLineRes::Unchanged
         }
     }
 
+    /// Delete from the cursor to the beginning of the n-th next line,
+    /// or to the end of the buffer. Zero deletes back to the beginning of the line.
+    pub fn kill_lines(&mut self, n: usize) {
+        let (x, y) = self.index_cursor();
+        if n == 0 {
+            self.replace_range(buf::Range { start: (0, y), end: (x, y) }, "");
+            return;
+        }
+        let last = self.line_num() - 1;
+        let end = if y + n > last {
+            (self.line_str(last).chars().count(), last)
+        } else {
+            (0, y + n)
+        };
+        self.replace_range(buf::Range { start: (x, y), end: end }, "");
+    }
+
     /// Backspace.
     pub fn backspace(&mut self, limit: usize) -> BackspaceRes {
-        if self.cur.backspace() {
+        let (x, y) = self.index_cursor();
+        if let Some(text) = self.cur.backspace() {
+            self.touch();
+            self.record_delete((x - text.chars().count(), y), &text);
             BackspaceRes::Normal(self.after_cursor(limit))
         } else if let Some(line) = self.prevs.pop() {
+            self.touch();
+            let start = (line.len(), y - 1);
             self.cur.prepend(line);
             self.x = self.cur.x();
+            self.record_delete(start, "\n");
             BackspaceRes::PrevLine(self.cursor())
         } else {
             BackspaceRes::Unchanged
@@ -218,11 +609,677 @@ impl Buffer {
 
     /// Insert a char at the location of the cursur.
     pub fn insert(&mut self, c: char, limit: usize) -> term::String {
+        let start = self.index_cursor();
+        self.touch();
         self.cur.insert(c);
         self.x = self.x();
+        self.record_insert(start, &c.to_string());
         self.after_cursor(limit)
     }
 
+    /// Get the position of the cursor as (character index, line).
+    #[inline]
+    pub fn index_cursor(&self) -> hq::Pair {
+        (self.cur.index(), self.y())
+    }
+
+    /// Replace the range with the given text. The cursor is placed after the text.
+    pub fn replace_range(&mut self, range: buf::Range, text: &str) {
+        self.set_cursor(range.end.0, range.end.1);
+        let start = (range.start.1, range.start.0);
+        if (self.y(), self.cur.index()) > start {
+            if self.undos.is_empty() {
+                self.checkpoint();
+            }
+            // The deletion is recorded at once, not a character at a time.
+            let deleted = self.text(range);
+            let undos = mem::replace(&mut self.undos, vec![]);
+            while (self.y(), self.cur.index()) > start {
+                if let BackspaceRes::Unchanged = self.backspace(0) {
+                    break;
+                }
+            }
+            self.undos = undos;
+            let at = self.index_cursor();
+            self.record_delete(at, &deleted);
+        }
+        for c in text.chars() {
+            if c == '\n' {
+                self.break_line();
+            } else {
+                self.insert(c, 0);
+            }
+        }
+    }
+
+    /// Characters treated as a part of words, besides Unicode word segmentation.
+    #[inline]
+    pub fn set_word_chars(&mut self, value: &str) {
+        self.word_chars = String::from(value);
+    }
+
+    /// The line as a plain string.
+    fn line_str(&mut self, y: usize) -> String {
+        self.get(y).map_or_else(String::new, |s| s.to_str())
+    }
+
+    /// The text in the range.
+    pub fn text(&mut self, range: buf::Range) -> String {
+        let mut res = String::new();
+        for y in range.start.1..range.end.1 + 1 {
+            let line = self.line_str(y);
+            let len = line.chars().count();
+            if let Some((from, to)) = range.columns(y, len) {
+                res.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+            }
+            if y != range.end.1 {
+                res.push('\n');
+            }
+        }
+        res
+    }
+
+    /// The end of the word at or after the position, across lines.
+    fn forward_word_from(&mut self, (mut x, mut y): hq::Pair) -> hq::Pair {
+        loop {
+            let line = self.line_str(y);
+            if let Some(&(_, end)) = buf::word::words(&line, &self.word_chars)
+                .iter()
+                .find(|w| w.1 > x)
+            {
+                return (end, y);
+            }
+            if y + 1 >= self.line_num() {
+                return (line.chars().count(), y);
+            }
+            x = 0;
+            y += 1;
+        }
+    }
+
+    /// The beginning of the word before the position, across lines.
+    fn backward_word_from(&mut self, (mut x, mut y): hq::Pair) -> hq::Pair {
+        loop {
+            let line = self.line_str(y);
+            if let Some(&(start, _)) = buf::word::words(&line, &self.word_chars)
+                .iter()
+                .rev()
+                .find(|w| w.0 < x)
+            {
+                return (start, y);
+            }
+            if y == 0 {
+                return (0, 0);
+            }
+            x = usize::max_value();
+            y -= 1;
+        }
+    }
+
+    /// Move to the end of the next word.
+    pub fn forward_word(&mut self) -> hq::Pair {
+        let (x, y) = self.forward_word_from(self.index_cursor());
+        self.set_cursor(x, y);
+        self.cursor()
+    }
+
+    /// Move to the beginning of the previous word.
+    pub fn backward_word(&mut self) -> hq::Pair {
+        let (x, y) = self.backward_word_from(self.index_cursor());
+        self.set_cursor(x, y);
+        self.cursor()
+    }
+
+    /// Delete to the end of the next word, or to the beginning of the previous word.
+    pub fn kill_word(&mut self, forward: bool) {
+        let cursor = self.index_cursor();
+        let range = if forward {
+            buf::Range {
+                start: cursor,
+                end: self.forward_word_from(cursor),
+            }
+        } else {
+            buf::Range {
+                start: self.backward_word_from(cursor),
+                end: cursor,
+            }
+        };
+        self.replace_range(range, "");
+    }
+
+    /// Swap the characters around the cursor, and move forward.
+    /// At the end of the line, swap the last two characters.
+    pub fn transpose_chars(&mut self) -> bool {
+        let (x, y) = self.index_cursor();
+        let chars: Vec<char> = self.line_str(y).chars().collect();
+        if x == 0 || chars.len() < 2 {
+            return false;
+        }
+        let x = if x == chars.len() { x - 1 } else { x };
+        let text: String = vec![chars[x], chars[x - 1]].into_iter().collect();
+        self.replace_range(
+            buf::Range {
+                start: (x - 1, y),
+                end: (x + 1, y),
+            },
+            &text,
+        );
+        true
+    }
+
+    /// Swap the words around the cursor, and move after them.
+    pub fn transpose_words(&mut self) -> bool {
+        let end2 = self.forward_word_from(self.index_cursor());
+        let start2 = self.backward_word_from(end2);
+        let start1 = self.backward_word_from(start2);
+        let end1 = self.forward_word_from(start1);
+        if (start1.1, start1.0) >= (start2.1, start2.0) {
+            return false;
+        }
+        let w1 = self.text(buf::Range { start: start1, end: end1 });
+        let mid = self.text(buf::Range { start: end1, end: start2 });
+        let w2 = self.text(buf::Range { start: start2, end: end2 });
+        self.replace_range(
+            buf::Range {
+                start: start1,
+                end: end2,
+            },
+            &format!("{}{}{}", w2, mid, w1),
+        );
+        true
+    }
+
+    /// Convert the text to the end of the next word.
+    pub fn convert_word<F: Fn(&str) -> String>(&mut self, f: F) {
+        let cursor = self.index_cursor();
+        let range = buf::Range {
+            start: cursor,
+            end: self.forward_word_from(cursor),
+        };
+        let text = self.text(range);
+        self.replace_range(range, &f(&text));
+    }
+
+    #[inline]
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn set_tab_width(&mut self, value: usize) {
+        self.tab_width = value;
+        self.cur.set_tab_width(value);
+        self.x = self.cur.x();
+    }
+
+    /// True iff the indentation is made of tabs rather than spaces.
+    #[inline]
+    pub fn indent_tabs(&self) -> bool {
+        self.indent_tabs
+    }
+
+    #[inline]
+    pub fn set_indent_tabs(&mut self, value: bool) {
+        self.indent_tabs = value;
+    }
+
+    /// Insert a tab, or spaces up to the next tab stop.
+    pub fn insert_tab(&mut self) -> hq::Pair {
+        if self.indent_tabs {
+            self.insert('\t', 0);
+        } else {
+            for _ in 0..self.tab_width - self.x() % self.tab_width {
+                self.insert(' ', 0);
+            }
+        }
+        self.cursor()
+    }
+
+    #[inline]
+    pub fn mark(&self) -> Option<hq::Pair> {
+        self.mark
+    }
+
+    /// Set the mark at the cursor.
+    #[inline]
+    pub fn set_mark(&mut self) {
+        self.mark = Some(self.index_cursor());
+    }
+
+    /// The comment markers of the syntax.
+    #[inline]
+    pub fn comment_markers(&self) -> Option<buf::CommentMarkers> {
+        self.comment
+    }
+
+    #[inline]
+    pub fn set_comment_markers(&mut self, value: buf::CommentMarkers) {
+        self.comment = Some(value);
+    }
+
+    #[inline]
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    #[inline]
+    pub fn set_mode(&mut self, value: &str) {
+        self.mode = String::from(value);
+    }
+
+    /// Delete the whitespace at the end of every line, keeping the cursor in its line.
+    /// Return true iff any is deleted.
+    pub fn delete_trailing_whitespace(&mut self) -> bool {
+        let (x, y) = self.index_cursor();
+        let mut deleted = false;
+        for (i, line) in self.lines().iter().enumerate() {
+            let len = line.chars().count();
+            let trimmed = line.trim_right().chars().count();
+            if trimmed < len {
+                self.replace_range(
+                    buf::Range {
+                        start: (trimmed, i),
+                        end: (len, i),
+                    },
+                    "",
+                );
+                deleted = true;
+            }
+        }
+        if deleted {
+            let len = self.line_str(y).chars().count();
+            self.set_cursor(cmp::min(x, len), y);
+        }
+        deleted
+    }
+
+    /// Deactivate the mark.
+    #[inline]
+    pub fn clear_mark(&mut self) {
+        self.mark = None;
+    }
+
+    /// The lines `[from, to]` between the mark and the cursor, or the cursor line.
+    pub fn region_lines(&self) -> (usize, usize) {
+        let y = self.y();
+        match self.mark {
+            Some((_, m)) if m < y => (m, y),
+            Some((_, m)) => (y, m),
+            None => (y, y),
+        }
+    }
+
+    /// A level of indentation.
+    fn indent_unit(&self) -> String {
+        if self.indent_tabs {
+            String::from("\t")
+        } else {
+            " ".repeat(self.tab_width)
+        }
+    }
+
+    /// True iff a line break copies the indentation.
+    #[inline]
+    pub fn auto_indent(&self) -> bool {
+        self.auto_indent
+    }
+
+    #[inline]
+    pub fn set_auto_indent(&mut self, value: bool) {
+        self.auto_indent = value;
+    }
+
+    /// Break the line, and indent the new line as the previous one.
+    /// After an opening bracket, indent one more level, and put the closing one on its own line.
+    pub fn newline_and_indent(&mut self) -> hq::Pair {
+        let (idx, y) = self.index_cursor();
+        let line = self.line_str(y);
+        let before: String = line.chars().take(idx).collect();
+        let after: String = line.chars().skip(idx).collect();
+        let base = String::from(buf::indent::leading_whitespace(&line));
+        let opened = self.indent_rules.opens(&before);
+        let closed = after.trim_left().chars().next().map_or(false, |c| {
+            self.indent_rules.opening(c).is_some()
+        });
+        let spaces = after.chars().take_while(|&c| c == ' ' || c == '\t').count();
+        if spaces > 0 {
+            self.replace_range(buf::Range { start: (idx, y), end: (idx + spaces, y) }, "");
+        }
+        let indent = if opened {
+            format!("{}{}", base, self.indent_unit())
+        } else {
+            base.clone()
+        };
+        self.break_line();
+        self.replace_range(buf::Range { start: (0, y + 1), end: (0, y + 1) }, &indent);
+        if opened && closed {
+            let cursor = self.index_cursor();
+            self.break_line();
+            self.replace_range(buf::Range { start: (0, y + 2), end: (0, y + 2) }, &base);
+            self.set_cursor(cursor.0, cursor.1);
+        }
+        self.cursor()
+    }
+
+    /// The position of `pair` paired with the bracket `c` at the position.
+    /// Brackets in strings and comments are skipped.
+    fn find_pair(
+        &mut self,
+        (x, y): hq::Pair,
+        c: char,
+        pair: char,
+        forward: bool,
+    ) -> Option<hq::Pair> {
+        let lines: Vec<usize> = if forward {
+            (y..cmp::min(y + PAIR_SCAN_LINES, self.line_num())).collect()
+        } else {
+            (y.saturating_sub(PAIR_SCAN_LINES)..y + 1).rev().collect()
+        };
+        let mut depth = 0;
+        for line in lines {
+            let s = match self.get(line) {
+                Some(s) => s,
+                None => break,
+            };
+            let found = if forward {
+                let from = if line == y { x + 1 } else { 0 };
+                scan_pair(s.iter().enumerate().skip(from), c, pair, &mut depth)
+            } else {
+                let end = if line == y { x } else { s.len() };
+                scan_pair(s.iter().enumerate().take(end).rev(), c, pair, &mut depth)
+            };
+            if let Some(i) = found {
+                return Some((i, line));
+            }
+        }
+        None
+    }
+
+    /// The bracket at the cursor, or the closing one just before it, and its pair.
+    pub fn matching_bracket(&mut self) -> Option<(hq::Pair, hq::Pair)> {
+        let (x, y) = self.index_cursor();
+        let found = {
+            let s = self.get(y)?;
+            let bracket = |i: usize| match s.iter().nth(i) {
+                Some(chr) if !chr.literal => {
+                    let c = chr.chr;
+                    buf::indent::bracket_pair(c).map(|(pair, forward)| (i, c, pair, forward))
+                }
+                _ => None,
+            };
+            match bracket(x) {
+                Some(b) => Some(b),
+                None => match x.checked_sub(1).and_then(|i| bracket(i)) {
+                    Some(b) if !b.3 => Some(b),
+                    _ => None,
+                },
+            }
+        };
+        let (i, c, pair, forward) = found?;
+        self.find_pair((i, y), c, pair, forward).map(|p| ((i, y), p))
+    }
+
+    /// Move the cursor to the pair of the bracket. Return false iff there is none.
+    pub fn jump_to_matching_bracket(&mut self) -> bool {
+        match self.matching_bracket() {
+            Some((_, (x, y))) => {
+                self.set_cursor(x, y);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The ranges of the bracket at the cursor and its pair.
+    /// They are found again only after the cursor moves or the buffer is modified.
+    pub fn bracket_highlights(&mut self) -> Vec<buf::Range> {
+        let cursor = self.index_cursor();
+        match self.brackets {
+            Some((at, ref ranges)) if at == cursor => return ranges.clone(),
+            _ => (),
+        }
+        let ranges: Vec<buf::Range> = match self.matching_bracket() {
+            Some((a, b)) => vec![a, b]
+                .into_iter()
+                .map(|(x, y)| buf::Range { start: (x, y), end: (x + 1, y) })
+                .collect(),
+            None => vec![],
+        };
+        self.brackets = Some((cursor, ranges.clone()));
+        ranges
+    }
+
+    /// If the closing bracket just typed starts the line, align it with its opening pair,
+    /// unless the auto-indentation is off.
+    /// Return true iff the indentation is changed.
+    pub fn dedent_closing(&mut self) -> bool {
+        if !self.auto_indent {
+            return false;
+        }
+        let (idx, y) = self.index_cursor();
+        let line = self.line_str(y);
+        let before: Vec<char> = line.chars().take(idx).collect();
+        let close = match before.last() {
+            Some(&c) => c,
+            None => return false,
+        };
+        let open = match self.indent_rules.opening(close) {
+            Some(open) => open,
+            None => return false,
+        };
+        let n = before.len() - 1;
+        if before[..n].iter().any(|&c| c != ' ' && c != '\t') {
+            return false;
+        }
+        let indent = match self.find_pair((n, y), close, open, false) {
+            Some((_, line)) => String::from(buf::indent::leading_whitespace(&self.line_str(line))),
+            None => return false,
+        };
+        if before[..n].iter().cloned().collect::<String>() == indent {
+            return false;
+        }
+        self.replace_range(buf::Range { start: (0, y), end: (n, y) }, &indent);
+        let x = indent.chars().count() + 1;
+        self.set_cursor(x, y);
+        true
+    }
+
+    /// Comment out the lines `[from, to]` at their minimum indentation,
+    /// or uncomment them if every non-blank line is commented out.
+    /// Return false iff the syntax has no comment.
+    pub fn toggle_comment(&mut self, from: usize, to: usize) -> bool {
+        let markers = match self.comment {
+            Some(markers) => markers,
+            None => return false,
+        };
+        let lines: Vec<String> = (from..to + 1).map(|y| self.line_str(y)).collect();
+        let body = |s: &str| s[buf::indent::leading_whitespace(s).len()..].to_string();
+        let filled: Vec<&String> = lines.iter().filter(|s| !s.trim().is_empty()).collect();
+        let uncomment = !filled.is_empty() && filled.iter().all(|s| markers.is_commented(&body(s)));
+        let column = filled
+            .iter()
+            .map(|s| buf::indent::leading_whitespace(s).chars().count())
+            .min()
+            .unwrap_or(0);
+        let (x, y) = self.index_cursor();
+        let mut cursor_x = x;
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent = buf::indent::leading_whitespace(line);
+            let (new, head) = if uncomment {
+                let text = body(line);
+                (format!("{}{}", indent, markers.uncomment(&text)), indent.chars().count())
+            } else {
+                let (head, tail): (String, String) = (
+                    line.chars().take(column).collect(),
+                    line.chars().skip(column).collect(),
+                );
+                (format!("{}{}{}{}", head, markers.start, tail, markers.end), column)
+            };
+            if from + i == y && x > head {
+                // Keep the cursor on the same text.
+                cursor_x = if uncomment {
+                    let prefix = markers.prefix_len(&body(line));
+                    cmp::max(x.saturating_sub(prefix), head)
+                } else {
+                    x + markers.start.chars().count()
+                };
+            }
+            let len = line.chars().count();
+            self.replace_range(
+                buf::Range {
+                    start: (0, from + i),
+                    end: (len, from + i),
+                },
+                &new,
+            );
+        }
+        self.set_cursor(cursor_x, y);
+        true
+    }
+
+    /// Add a level of indentation to the lines `[from, to]`.
+    pub fn indent_lines(&mut self, from: usize, to: usize) {
+        let unit = self.indent_unit();
+        let (x, y) = self.index_cursor();
+        for line in from..to + 1 {
+            if !self.line_str(line).is_empty() {
+                self.replace_range(buf::Range { start: (0, line), end: (0, line) }, &unit);
+            }
+        }
+        let x = if from <= y && y <= to && !self.line_str(y).is_empty() {
+            x + unit.chars().count()
+        } else {
+            x
+        };
+        self.set_cursor(x, y);
+    }
+
+    /// Remove a level of indentation, a tab or up to `tab_width` spaces, from the lines.
+    pub fn outdent_lines(&mut self, from: usize, to: usize) {
+        let (mut x, y) = self.index_cursor();
+        for line in from..to + 1 {
+            let s = self.line_str(line);
+            let n = if s.starts_with('\t') {
+                1
+            } else {
+                s.chars().take(self.tab_width).take_while(|&c| c == ' ').count()
+            };
+            if n > 0 {
+                self.replace_range(buf::Range { start: (0, line), end: (n, line) }, "");
+            }
+            if line == y {
+                x = x.saturating_sub(n);
+            }
+        }
+        self.set_cursor(x, y);
+    }
+
+    /// Ranges to be highlighted, e.g. the current match of search.
+    #[inline]
+    pub fn highlights(&self) -> &[buf::Range] {
+        &self.highlights
+    }
+
+    /// Set the ranges to be highlighted.
+    #[inline]
+    pub fn set_highlights(&mut self, value: Vec<buf::Range>) {
+        self.highlights = value;
+    }
+
+    /// Every line with colors.
+    #[cfg(test)]
+    fn strings(&mut self) -> Vec<term::String> {
+        let mut res = self.prevs.clone();
+        res.push(self.cur.as_string().clone());
+        res.extend(self.nexts.iter().rev().cloned());
+        res
+    }
+
+    /// Start a new unit of undo at the current state.
+    pub fn checkpoint(&mut self) {
+        let cursor = self.index_cursor();
+        if let Some(undo) = self.undos.last_mut() {
+            if undo.changes.is_empty() {
+                undo.cursor = cursor;
+                return;
+            }
+        }
+        if self.undos.len() >= UNDO_LIMIT {
+            self.undos.remove(0);
+        }
+        self.undos.push(Undo {
+            cursor: cursor,
+            changes: vec![],
+        });
+    }
+
+    /// Record the edit into the current unit, merging it into the last edit if adjacent.
+    fn record(&mut self, change: Change) {
+        if self.undos.is_empty() {
+            self.checkpoint();
+        }
+        let changes = &mut self.undos.last_mut().unwrap().changes;
+        match (changes.last_mut(), &change) {
+            (Some(&mut Change::Insert(ref mut range)), &Change::Insert(r)) => {
+                if range.end == r.start {
+                    range.end = r.end;
+                    return;
+                }
+            }
+            (Some(&mut Change::Delete(ref mut at, ref mut s)), &Change::Delete(p, ref t)) => {
+                if end_of(p, t) == *at {
+                    // Deleted backward.
+                    *at = p;
+                    s.insert_str(0, t);
+                    return;
+                } else if p == *at {
+                    s.push_str(t);
+                    return;
+                }
+            }
+            _ => {}
+        }
+        changes.push(change);
+    }
+
+    #[inline]
+    fn record_insert(&mut self, start: hq::Pair, text: &str) {
+        self.record(Change::Insert(buf::Range {
+            start: start,
+            end: end_of(start, text),
+        }));
+    }
+
+    #[inline]
+    fn record_delete(&mut self, start: hq::Pair, text: &str) {
+        self.record(Change::Delete(start, String::from(text)));
+    }
+
+    /// Revert the edits since the last checkpoint. Return false iff there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        while let Some(undo) = self.undos.pop() {
+            if undo.changes.is_empty() {
+                continue;
+            }
+            // Reverting is not recorded.
+            let undos = mem::replace(&mut self.undos, vec![]);
+            for change in undo.changes.into_iter().rev() {
+                match change {
+                    Change::Insert(range) => self.replace_range(range, ""),
+                    Change::Delete(at, text) => {
+                        self.replace_range(buf::Range { start: at, end: at }, &text)
+                    }
+                }
+            }
+            self.undos = undos;
+            self.set_cursor(undo.cursor.0, undo.cursor.1);
+            return true;
+        }
+        false
+    }
+
     /// Convert to a string.
     /// This can be used for the debugging purpose.
     #[cfg(test)]
@@ -241,3 +1298,246 @@ impl Buffer {
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_empty_file() {
+        let path = env::temp_dir().join("thief_test_empty_file");
+        fs::File::create(&path).unwrap();
+        let mut buffer = Buffer::from_file(&path).unwrap();
+        assert_eq!(buffer.line_num(), 1);
+        assert_eq!(buffer.to_str(), "\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_new_file() {
+        let dir = env::temp_dir().join("thief_test_new_file");
+        let path = dir.join("sub").join("new.txt");
+        let mut buffer = Buffer::new_file(&path);
+        assert!(buffer.is_new_file());
+        assert!(!buffer.parent_exists());
+        assert_eq!(buffer.line_num(), 1);
+        buffer.insert('a', 80);
+        buffer.create_parent().unwrap();
+        buffer.save().unwrap();
+        assert!(!buffer.is_new_file() && !buffer.modified());
+        assert_eq!(Buffer::from_file(&path).unwrap().to_str(), "a\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recover_and_revert() {
+        let dir = env::temp_dir().join("thief_test_recover");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.rs");
+        fs::File::create(&path).unwrap().write_all(b"fn a() {}\n").unwrap();
+        let mut buffer = Buffer::from_file(&path).unwrap();
+        buffer.insert('b', 80);
+        assert!(buffer.auto_save().unwrap());
+        buffer.set_mode("rust");
+        buffer.set_tab_width(2);
+        buffer.set_word_chars("-");
+        buffer.recover().unwrap();
+        assert_eq!(buffer.to_str(), "bfn a() {}\n");
+        assert_eq!(buffer.mode(), "rust");
+        // Highlighted as Rust, not as the `rs#` extension.
+        let rust = dir.join("b.rs");
+        fs::File::create(&rust).unwrap().write_all(b"bfn a() {}\n").unwrap();
+        let expected = Buffer::from_file(&rust).unwrap().strings();
+        assert_eq!(format!("{:?}", buffer.strings()), format!("{:?}", expected));
+        // The format is detected again from the file changed on the disk.
+        fs::File::create(&path).unwrap().write_all(b"fn a() {}\r\n").unwrap();
+        buffer.revert().unwrap();
+        assert_eq!(buffer.to_str(), "fn a() {}\n");
+        assert_eq!(buffer.format().line_ending, buf::LineEnding::CrLf);
+        assert_eq!(buffer.mode(), "rust");
+        assert_eq!(buffer.tab_width(), 2);
+        assert_eq!(buffer.word_chars, "-");
+        assert!(!buffer.recovery_path().unwrap().exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_range_and_undo() {
+        let mut buffer = Buffer::from_string("foo bar\nbaz");
+        buffer.checkpoint();
+        buffer.replace_range(
+            buf::Range {
+                start: (4, 0),
+                end: (1, 1),
+            },
+            "x\ny",
+        );
+        assert_eq!(buffer.to_str(), "foo x\nyaz\n");
+        assert_eq!(buffer.index_cursor(), (1, 1));
+        assert_eq!(buffer.undos.last().unwrap().changes.len(), 2);
+        assert!(buffer.undo());
+        assert_eq!(buffer.to_str(), "foo bar\nbaz\n");
+        assert!(!buffer.undo());
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut buffer = Buffer::from_string("x\ny");
+        buffer.checkpoint();
+        buffer.insert('a', 0);
+        buffer.break_line();
+        buffer.insert('b', 0);
+        buffer.checkpoint();
+        buffer.set_cursor(0, 2);
+        buffer.backspace(0);
+        buffer.backspace(0);
+        buffer.checkpoint();
+        buffer.kill_line();
+        buffer.kill_line();
+        assert_eq!(buffer.to_str(), "a\nb\n");
+        assert!(buffer.undo());
+        assert_eq!(buffer.to_str(), "a\nby\n");
+        assert!(buffer.undo());
+        assert_eq!(buffer.to_str(), "a\nbx\ny\n");
+        assert_eq!(buffer.index_cursor(), (1, 1));
+        assert!(buffer.undo());
+        assert_eq!(buffer.to_str(), "x\ny\n");
+        assert_eq!(buffer.index_cursor(), (0, 0));
+        assert!(!buffer.undo());
+    }
+
+    #[test]
+    fn test_word_motion() {
+        let mut buffer = Buffer::from_string("foo bar\n  baz");
+        assert_eq!(buffer.forward_word(), (3, 0));
+        assert_eq!(buffer.forward_word(), (7, 0));
+        assert_eq!(buffer.forward_word(), (5, 1));
+        assert_eq!(buffer.backward_word(), (2, 1));
+        assert_eq!(buffer.backward_word(), (4, 0));
+        buffer.kill_word(false);
+        assert_eq!(buffer.to_str(), "bar\n  baz\n");
+        buffer.set_cursor(3, 0);
+        buffer.kill_word(true);
+        assert_eq!(buffer.to_str(), "bar\n");
+    }
+
+    #[test]
+    fn test_word_editing() {
+        let mut buffer = Buffer::from_string("abc hello world");
+        buffer.set_cursor(1, 0);
+        assert!(buffer.transpose_chars());
+        assert_eq!(buffer.to_str(), "bac hello world\n");
+        buffer.convert_word(|s| s.to_uppercase());
+        assert_eq!(buffer.to_str(), "baC hello world\n");
+        buffer.convert_word(buf::capitalize);
+        assert_eq!(buffer.to_str(), "baC Hello world\n");
+        assert!(buffer.transpose_words());
+        assert_eq!(buffer.to_str(), "baC world Hello\n");
+        assert_eq!(buffer.index_cursor(), (15, 0));
+    }
+
+    #[test]
+    fn test_tabs_and_indent() {
+        let mut buffer = Buffer::from_string("a\tb\nc");
+        buffer.set_tab_width(4);
+        buffer.set_cursor(2, 0);
+        assert_eq!(buffer.cursor(), (4, 0));
+        buffer.set_mark();
+        buffer.set_cursor(0, 1);
+        let (from, to) = buffer.region_lines();
+        buffer.indent_lines(from, to);
+        assert_eq!(buffer.to_str(), "    a\tb\n    c\n");
+        assert_eq!(buffer.index_cursor(), (4, 1));
+        buffer.set_indent_tabs(true);
+        buffer.insert_tab();
+        assert_eq!(buffer.cursor(), (8, 1));
+        buffer.outdent_lines(0, 1);
+        assert_eq!(buffer.to_str(), "a\tb\n\tc\n");
+        assert_eq!(buffer.cursor(), (4, 1));
+    }
+
+    #[test]
+    fn test_auto_indent() {
+        let mut buffer = Buffer::from_string("    if x {}");
+        buffer.set_tab_width(4);
+        buffer.set_cursor(10, 0);
+        buffer.newline_and_indent();
+        assert_eq!(buffer.to_str(), "    if x {\n        \n    }\n");
+        assert_eq!(buffer.index_cursor(), (8, 1));
+        buffer.insert('a', 0);
+        buffer.newline_and_indent();
+        assert_eq!(buffer.index_cursor(), (8, 2));
+        buffer.insert('}', 0);
+        assert!(buffer.dedent_closing());
+        assert_eq!(buffer.to_str(), "    if x {\n        a\n    }\n    }\n");
+        assert_eq!(buffer.index_cursor(), (5, 2));
+    }
+
+    #[test]
+    fn test_toggle_comment() {
+        let mut buffer = Buffer::from_string("  a\n\n    b");
+        buffer.comment = buf::CommentMarkers::from_syntax("Rust");
+        buffer.set_cursor(4, 2);
+        assert!(buffer.toggle_comment(0, 2));
+        assert_eq!(buffer.to_str(), "  // a\n\n  //   b\n");
+        assert_eq!(buffer.index_cursor(), (7, 2));
+        assert!(buffer.toggle_comment(0, 2));
+        assert_eq!(buffer.to_str(), "  a\n\n    b\n");
+        assert_eq!(buffer.index_cursor(), (4, 2));
+        buffer.comment = None;
+        assert!(!buffer.toggle_comment(0, 0));
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let mut buffer = Buffer::from_string("f(a, \"(\",\n  [b])");
+        // Mark the string literal as syntect does.
+        buffer.set_cursor(0, 1);
+        for c in buffer.prevs[0].iter_mut().skip(5).take(3) {
+            c.literal = true;
+        }
+        buffer.set_cursor(1, 0);
+        assert_eq!(buffer.matching_bracket(), Some(((1, 0), (5, 1))));
+        buffer.set_cursor(6, 1);
+        assert!(buffer.jump_to_matching_bracket());
+        assert_eq!(buffer.index_cursor(), (1, 0));
+        buffer.set_cursor(6, 1);
+        assert_eq!(buffer.bracket_highlights().len(), 2);
+        buffer.set_cursor(2, 1);
+        assert_eq!(buffer.matching_bracket(), Some(((2, 1), (4, 1))));
+        buffer.set_cursor(6, 0);
+        assert_eq!(buffer.matching_bracket(), None);
+        assert!(!buffer.jump_to_matching_bracket());
+        // The bracket typed in the string is a literal too.
+        buffer.set_cursor(7, 0);
+        buffer.insert(')', 0);
+        buffer.set_cursor(1, 0);
+        assert_eq!(buffer.matching_bracket(), Some(((1, 0), (5, 1))));
+    }
+
+    #[test]
+    fn test_kill_lines() {
+        let mut buffer = Buffer::from_string("ab\ncd\nef\ngh");
+        buffer.set_cursor(1, 0);
+        buffer.kill_lines(2);
+        assert_eq!(buffer.to_str(), "aef\ngh\n");
+        buffer.kill_lines(5);
+        assert_eq!(buffer.to_str(), "a\n");
+        let mut buffer = Buffer::from_string("ab\ncde");
+        buffer.set_cursor(2, 1);
+        buffer.kill_lines(0);
+        assert_eq!(buffer.to_str(), "ab\ne\n");
+        assert_eq!(buffer.index_cursor(), (0, 1));
+    }
+
+    #[test]
+    fn test_delete_trailing_whitespace() {
+        let mut buffer = Buffer::from_string("ab  \ncd\t\n  ");
+        buffer.set_cursor(4, 0);
+        assert!(buffer.delete_trailing_whitespace());
+        assert_eq!(buffer.to_str(), "ab\ncd\n\n");
+        assert_eq!(buffer.index_cursor(), (2, 0));
+        assert!(!buffer.delete_trailing_whitespace());
+    }
+}
@@ -0,0 +1,89 @@
+/// The comment markers of a syntax, kept by hand for the syntect syntax names.
+/// Syntaxes without comments, such as JSON, have none.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CommentMarkers {
+    pub start: &'static str,
+    pub end: &'static str,
+}
+
+fn line(start: &'static str) -> Option<CommentMarkers> {
+    Some(CommentMarkers {
+        start: start,
+        end: "",
+    })
+}
+
+impl CommentMarkers {
+    /// The markers for the name of the syntect syntax.
+    pub fn from_syntax(name: &str) -> Option<CommentMarkers> {
+        match name {
+            "Rust" | "C" | "C++" | "C#" | "Objective-C" | "Objective-C++" | "Java" |
+            "JavaScript" | "TypeScript" | "Go" | "Scala" | "Swift" | "PHP" | "D" |
+            "Groovy" => line("// "),
+            "Python" | "Ruby" | "Perl" | "R" | "YAML" | "TOML" | "Makefile" |
+            "Shell-Unix-Generic" | "Bourne Again Shell (bash)" | "Dockerfile" => line("# "),
+            "Lua" | "SQL" | "Haskell" | "Ada" => line("-- "),
+            "Lisp" | "Clojure" | "Scheme" => line("; "),
+            "LaTeX" | "TeX" | "Erlang" | "MATLAB" => line("% "),
+            "Batch File" => line("REM "),
+            "OCaml" | "Pascal" => Some(CommentMarkers {
+                start: "(* ",
+                end: " *)",
+            }),
+            "CSS" => Some(CommentMarkers {
+                start: "/* ",
+                end: " */",
+            }),
+            "HTML" | "XML" | "Markdown" => Some(CommentMarkers {
+                start: "<!-- ",
+                end: " -->",
+            }),
+            _ => None,
+        }
+    }
+
+    /// True iff the line without its indentation is commented out.
+    pub fn is_commented(&self, s: &str) -> bool {
+        let (start, end, s) = (self.start.trim_right(), self.end.trim_left(), s.trim_right());
+        s.len() >= start.len() + end.len() && s.starts_with(start) && s.ends_with(end)
+    }
+
+    /// The length of the start marker in the commented line, with its padding space.
+    pub fn prefix_len(&self, s: &str) -> usize {
+        let start = self.start.trim_right();
+        let padded = self.start.len() > start.len() && s[start.len()..].starts_with(' ');
+        start.len() + if padded { 1 } else { 0 }
+    }
+
+    /// Remove the markers and their padding spaces from the line without its indentation.
+    pub fn uncomment<'a>(&self, s: &'a str) -> &'a str {
+        let s = s[self.prefix_len(s)..].trim_right();
+        let end = self.end.trim_left();
+        let s = &s[..s.len() - end.len()];
+        if self.end.len() > end.len() && s.ends_with(' ') {
+            &s[..s.len() - 1]
+        } else {
+            s
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let rust = CommentMarkers::from_syntax("Rust").unwrap();
+        assert!(rust.is_commented("//x"));
+        assert_eq!(rust.uncomment("// let x;"), "let x;");
+        assert_eq!(rust.uncomment("//   x"), "  x");
+        let css = CommentMarkers::from_syntax("CSS").unwrap();
+        assert!(css.is_commented("/* a: b; */"));
+        assert!(!css.is_commented("/* a: b;"));
+        assert!(!css.is_commented("/*/"));
+        assert_eq!(css.uncomment("/*a: b;*/"), "a: b;");
+        assert!(CommentMarkers::from_syntax("Plain Text").is_none());
+        assert!(CommentMarkers::from_syntax("JSON").is_none());
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+use std::str;
+
+def_error! {
+    Unencodable: "the buffer has characters which cannot be encoded.",
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+/// How the buffer is stored in the file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Format {
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    pub final_newline: bool,
+}
+
+const UTF8_BOM: &'static [u8] = b"\xef\xbb\xbf";
+const UTF16LE_BOM: &'static [u8] = b"\xff\xfe";
+const UTF16BE_BOM: &'static [u8] = b"\xfe\xff";
+
+/// Decode UTF-16 without BOM.
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> Option<String> {
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|c| if little_endian {
+            (c[1] as u16) << 8 | c[0] as u16
+        } else {
+            (c[0] as u16) << 8 | c[1] as u16
+        })
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+impl Encoding {
+    pub fn from_name(s: &str) -> Option<Encoding> {
+        match s.trim().to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(Encoding::Utf8Bom),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    /// Detect the encoding and decode.
+    /// Invalid sequences fall back to Latin-1, which keeps every byte as it is.
+    pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+        if bytes.starts_with(UTF8_BOM) {
+            if let Ok(s) = str::from_utf8(&bytes[UTF8_BOM.len()..]) {
+                return (String::from(s), Encoding::Utf8Bom);
+            }
+        } else if bytes.starts_with(UTF16LE_BOM) {
+            if let Some(s) = decode_utf16(&bytes[UTF16LE_BOM.len()..], true) {
+                return (s, Encoding::Utf16Le);
+            }
+        } else if bytes.starts_with(UTF16BE_BOM) {
+            if let Some(s) = decode_utf16(&bytes[UTF16BE_BOM.len()..], false) {
+                return (s, Encoding::Utf16Be);
+            }
+        } else if let Ok(s) = str::from_utf8(bytes) {
+            return (String::from(s), Encoding::Utf8);
+        }
+        (bytes.iter().map(|&b| b as char).collect(), Encoding::Latin1)
+    }
+
+    /// Encode the string, including BOM.
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>> {
+        match *self {
+            Encoding::Utf8 => Ok(s.as_bytes().to_vec()),
+            Encoding::Utf8Bom => {
+                let mut res = UTF8_BOM.to_vec();
+                res.extend_from_slice(s.as_bytes());
+                Ok(res)
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let little_endian = *self == Encoding::Utf16Le;
+                let mut res = if little_endian {
+                    UTF16LE_BOM.to_vec()
+                } else {
+                    UTF16BE_BOM.to_vec()
+                };
+                for u in s.encode_utf16() {
+                    let (hi, lo) = ((u >> 8) as u8, u as u8);
+                    if little_endian {
+                        res.push(lo);
+                        res.push(hi);
+                    } else {
+                        res.push(hi);
+                        res.push(lo);
+                    }
+                }
+                Ok(res)
+            }
+            Encoding::Latin1 => {
+                s.chars()
+                    .map(|c| if (c as u32) < 0x100 {
+                        Ok(c as u8)
+                    } else {
+                        Err(Error::Unencodable)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl LineEnding {
+    pub fn from_name(s: &str) -> Option<LineEnding> {
+        match s.trim().to_lowercase().as_str() {
+            "lf" | "unix" => Some(LineEnding::Lf),
+            "crlf" | "dos" => Some(LineEnding::CrLf),
+            "cr" | "mac" => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+            LineEnding::Cr => "cr",
+        }
+    }
+
+    /// The line separator.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Split the text at every line break of any kind, and detect the ending of the most
+    /// line breaks; the first one among the ties.
+    pub fn split(s: &str) -> (Vec<String>, LineEnding) {
+        let mut lines = vec![];
+        // The number of each ending, in the order of the appearance.
+        let mut counts: Vec<(LineEnding, usize)> = vec![];
+        let bytes = s.as_bytes();
+        let (mut start, mut i) = (0, 0);
+        while i < bytes.len() {
+            let ending = match bytes[i] {
+                b'\n' => LineEnding::Lf,
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => LineEnding::CrLf,
+                b'\r' => LineEnding::Cr,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            lines.push(String::from(&s[start..i]));
+            i += ending.as_str().len();
+            start = i;
+            match counts.iter().position(|&(e, _)| e == ending) {
+                Some(j) => counts[j].1 += 1,
+                None => counts.push((ending, 1)),
+            }
+        }
+        lines.push(String::from(&s[start..]));
+        let mut res = (LineEnding::Lf, 0);
+        for (ending, n) in counts {
+            if n > res.1 {
+                res = (ending, n);
+            }
+        }
+        (lines, res.0)
+    }
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format {
+            encoding: Encoding::Utf8,
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.encoding.name(), self.line_ending.name())
+    }
+}
+
+impl Format {
+    /// Detect the format, and split the content into lines.
+    pub fn decode(bytes: &[u8]) -> (Vec<String>, Format) {
+        let (text, encoding) = Encoding::decode(bytes);
+        let (mut lines, line_ending) = LineEnding::split(&text);
+        // `split` leaves an empty string after the final line break.
+        let final_newline = lines.len() > 1 && lines.last().map_or(false, |l| l.is_empty());
+        if final_newline {
+            lines.pop();
+        }
+        (
+            lines,
+            Format {
+                encoding: encoding,
+                line_ending: line_ending,
+                final_newline: final_newline,
+            },
+        )
+    }
+
+    /// Join the lines, and encode.
+    pub fn encode(&self, lines: &[String]) -> Result<Vec<u8>> {
+        let mut text = lines.join(self.line_ending.as_str());
+        if self.final_newline {
+            text.push_str(self.line_ending.as_str());
+        }
+        self.encoding.encode(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8], encoding: Encoding, line_ending: LineEnding) -> Vec<String> {
+        let (lines, format) = Format::decode(bytes);
+        assert_eq!(format.encoding, encoding);
+        assert_eq!(format.line_ending, line_ending);
+        assert_eq!(format.encode(&lines).unwrap(), bytes);
+        lines
+    }
+
+    #[test]
+    fn test_line_ending() {
+        assert_eq!(round_trip(b"a\r\nb\r\n", Encoding::Utf8, LineEnding::CrLf), vec!["a", "b"]);
+        assert_eq!(round_trip(b"a\rb", Encoding::Utf8, LineEnding::Cr), vec!["a", "b"]);
+        assert_eq!(round_trip(b"a\nb\n", Encoding::Utf8, LineEnding::Lf), vec!["a", "b"]);
+        assert_eq!(round_trip(b"", Encoding::Utf8, LineEnding::Lf), vec![""]);
+        assert_eq!(round_trip(b"\n", Encoding::Utf8, LineEnding::Lf), vec![""]);
+        // Mixed line breaks are saved with the most frequent one.
+        let (lines, format) = Format::decode(b"a\r\nb\nc\r\nd\re");
+        assert_eq!(lines, vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+        let (_, format) = Format::decode(b"a\rb\nc");
+        assert_eq!(format.line_ending, LineEnding::Cr);
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(
+            round_trip(b"\xef\xbb\xbf\xed\x95\x9c\n", Encoding::Utf8Bom, LineEnding::Lf),
+            vec!["\u{d55c}"]
+        );
+        assert_eq!(
+            round_trip(b"\xff\xfea\x00\n\x00", Encoding::Utf16Le, LineEnding::Lf),
+            vec!["a"]
+        );
+        assert_eq!(
+            round_trip(b"\xfe\xff\x00a\x00\r\x00\n", Encoding::Utf16Be, LineEnding::CrLf),
+            vec!["a"]
+        );
+        // Invalid UTF-8 survives as Latin-1.
+        assert_eq!(
+            round_trip(b"caf\xe9\n\xff\xfe", Encoding::Latin1, LineEnding::Lf),
+            vec!["caf\u{e9}", "\u{ff}\u{fe}"]
+        );
+        assert!(Encoding::Latin1.encode("\u{d55c}").is_err());
+    }
+}
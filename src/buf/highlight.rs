@@ -0,0 +1,95 @@
+use std::str::FromStr;
+use syntect::highlighting::{HighlightIterator, HighlightState, Highlighter, ScopeSelectors,
+                            Theme};
+use syntect::parsing::{ParseState, ScopeStack, ScopeStackOp, SyntaxDefinition};
+
+use term;
+
+/// Scopes where brackets are not paired.
+const LITERAL_SCOPES: &'static str = "string, comment";
+
+/// Highlight lines in order, marking the characters in strings and comments as literals.
+pub struct LineHighlighter<'a> {
+    highlighter: Highlighter<'a>,
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+    scopes: ScopeStack,
+    literal: ScopeSelectors,
+}
+
+impl<'a> LineHighlighter<'a> {
+    pub fn new(syntax: &SyntaxDefinition, theme: &'a Theme) -> LineHighlighter<'a> {
+        let highlighter = Highlighter::new(theme);
+        let highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+        LineHighlighter {
+            highlighter: highlighter,
+            parse_state: ParseState::new(syntax),
+            highlight_state: highlight_state,
+            scopes: ScopeStack::new(),
+            literal: ScopeSelectors::from_str(LITERAL_SCOPES).unwrap(),
+        }
+    }
+
+    /// Highlight the next line into the colored string.
+    pub fn highlight(&mut self, s: &str) -> term::String {
+        let ops = self.parse_state.parse_line(s);
+        let literals = self.literals(s, &ops);
+        let mut term_str = term::String::new();
+        let mut pos = 0;
+        for (style, token) in HighlightIterator::new(
+            &mut self.highlight_state,
+            &ops,
+            s,
+            &self.highlighter,
+        )
+        {
+            for (i, c) in token.char_indices() {
+                let mut chr = term::Char::new(c, term::Brush::from_syntect(style));
+                chr.literal = literals[pos + i];
+                term_str.push(chr);
+            }
+            pos += token.len();
+        }
+        term_str
+    }
+
+    /// Whether each byte of the line is in a literal scope.
+    fn literals(&mut self, s: &str, ops: &[(usize, ScopeStackOp)]) -> Vec<bool> {
+        let mut res = Vec::with_capacity(s.len());
+        let mut ops = ops.iter().peekable();
+        for i in 0..s.len() {
+            while let Some(&&(at, ref op)) = ops.peek() {
+                if at > i {
+                    break;
+                }
+                self.scopes.apply(op);
+                ops.next();
+            }
+            res.push(self.literal.does_match(self.scopes.as_slice()).is_some());
+        }
+        for &(_, ref op) in ops {
+            self.scopes.apply(op);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+    use syntect::parsing::SyntaxSet;
+
+    #[test]
+    fn test_literals() {
+        let ss = SyntaxSet::load_defaults_newlines();
+        let ts = ThemeSet::load_defaults();
+        let syntax = ss.find_syntax_by_name("Rust").unwrap();
+        let mut highlighter = LineHighlighter::new(syntax, &ts.themes["base16-ocean.dark"]);
+        let s = highlighter.highlight("f(\"(\") // )");
+        let literals: String = s.iter()
+            .map(|c| if c.literal { 'L' } else { c.chr })
+            .collect();
+        assert_eq!(literals, "f(LLL) LLLL");
+    }
+}
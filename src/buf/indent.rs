@@ -0,0 +1,96 @@
+/// How the indentation changes, by the syntax.
+#[derive(Clone, Copy, Debug)]
+pub struct IndentRules {
+    /// Brackets indenting the lines between the pair.
+    pub pairs: &'static [(char, char)],
+    /// Line endings indenting the next line, e.g. `:` of Python.
+    pub triggers: &'static [&'static str],
+}
+
+const BRACKETS: &'static [(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+impl Default for IndentRules {
+    fn default() -> IndentRules {
+        IndentRules {
+            pairs: BRACKETS,
+            triggers: &[],
+        }
+    }
+}
+
+impl IndentRules {
+    /// The rules for the name of the syntect syntax.
+    pub fn from_syntax(name: &str) -> IndentRules {
+        match name {
+            "Python" => IndentRules {
+                pairs: BRACKETS,
+                triggers: &[":"],
+            },
+            "Ruby" | "Lua" => IndentRules {
+                pairs: BRACKETS,
+                triggers: &["do", "then"],
+            },
+            _ => Default::default(),
+        }
+    }
+
+    /// True iff the text before a line break indents the next line.
+    pub fn opens(&self, s: &str) -> bool {
+        let s = s.trim_right();
+        s.chars().next_back().map_or(false, |c| {
+            self.pairs.iter().any(|p| p.0 == c)
+        }) || self.triggers.iter().any(|t| ends_with_word(s, t))
+    }
+
+    /// The opening pair of the closing bracket.
+    pub fn opening(&self, c: char) -> Option<char> {
+        self.pairs.iter().find(|p| p.1 == c).map(|p| p.0)
+    }
+}
+
+/// True iff `s` ends with `t`, which is not the tail of a longer word, e.g. `do` of `undo`.
+fn ends_with_word(s: &str, t: &str) -> bool {
+    fn is_word(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
+    }
+    s.ends_with(t) && (!t.starts_with(is_word) || !s[..s.len() - t.len()].ends_with(is_word))
+}
+
+/// The pair of the bracket, and true iff the pair follows it.
+pub fn bracket_pair(c: char) -> Option<(char, bool)> {
+    BRACKETS.iter().filter_map(|&(open, close)| if c == open {
+        Some((close, true))
+    } else if c == close {
+        Some((open, false))
+    } else {
+        None
+    }).next()
+}
+
+/// The leading spaces and tabs of the line.
+pub fn leading_whitespace(s: &str) -> &str {
+    let n = s.len() - s.trim_left_matches(|c| c == ' ' || c == '\t').len();
+    &s[..n]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens() {
+        let rules = IndentRules::from_syntax("Rust");
+        assert!(rules.opens("fn main() {  "));
+        assert!(!rules.opens("let x = f(y);"));
+        assert_eq!(rules.opening('}'), Some('{'));
+        assert!(IndentRules::from_syntax("Python").opens("if x:"));
+        let rules = IndentRules::from_syntax("Ruby");
+        assert!(rules.opens("items.each do"));
+        assert!(rules.opens("if x then"));
+        assert!(!rules.opens("# todo"));
+        assert!(!rules.opens("undo"));
+        assert_eq!(leading_whitespace("\t  x "), "\t  ");
+        assert_eq!(bracket_pair(')'), Some(('(', false)));
+        assert_eq!(bracket_pair('a'), None);
+    }
+}
@@ -1,7 +1,11 @@
 use std::mem;
 use term;
+use unicode_segmentation::UnicodeSegmentation;
 use util;
 
+/// The number of characters looked at on each side of the cursor to find grapheme clusters.
+const CLUSTER_WINDOW: usize = 32;
+
 #[derive(Debug)]
 pub struct Line {
     cache: term::String,
@@ -9,6 +13,7 @@ pub struct Line {
     prevs: term::String,
     nexts: term::String,
     x: usize,
+    tab_width: usize,
 }
 
 impl Default for Line {
@@ -19,6 +24,7 @@ impl Default for Line {
             prevs: term::String::new(),
             nexts: term::String::new(),
             x: Default::default(),
+            tab_width: util::DEFAULT_TAB_WIDTH,
         }
     }
 }
@@ -37,6 +43,70 @@ impl Line {
         self.x
     }
 
+    /// Set the width of tabs, which changes the terminal x.
+    pub fn set_tab_width(&mut self, value: usize) {
+        self.tab_width = value;
+        self.update_x();
+    }
+
+    /// Recalculate the terminal x from the characters before the cursor.
+    #[inline]
+    fn update_x(&mut self) {
+        self.x = self.prevs.column(self.prevs.len(), self.tab_width);
+    }
+
+    /// The characters just before the cursor, at most `CLUSTER_WINDOW` of them.
+    fn prev_window(&self) -> String {
+        let mut chars: Vec<char> =
+            self.prevs.iter().rev().take(CLUSTER_WINDOW).map(|c| c.chr).collect();
+        chars.reverse();
+        chars.into_iter().collect()
+    }
+
+    /// The characters just after the cursor, at most `CLUSTER_WINDOW` of them.
+    fn next_window(&self) -> String {
+        self.nexts.iter().rev().take(CLUSTER_WINDOW).map(|c| c.chr).collect()
+    }
+
+    /// The number of characters of the grapheme cluster before the cursor.
+    fn prev_cluster(&self) -> usize {
+        let s = self.prev_window();
+        s.graphemes(true).next_back().map_or(0, |g| g.chars().count())
+    }
+
+    /// The number of characters of the grapheme cluster after the cursor.
+    fn next_cluster(&self) -> usize {
+        let s = self.next_window();
+        s.graphemes(true).next().map_or(0, |g| g.chars().count())
+    }
+
+    /// Move the cursor forward to the boundary of grapheme clusters.
+    fn snap(&mut self) {
+        let mut s = self.prev_window();
+        let n = s.chars().count();
+        s.push_str(&self.next_window());
+        let mut boundary = 0;
+        for g in s.graphemes(true) {
+            if boundary >= n {
+                break;
+            }
+            boundary += g.chars().count();
+        }
+        let boundary = self.prevs.len() - n + boundary;
+        while self.prevs.len() < boundary {
+            if let Some(c) = self.nexts.pop() {
+                self.prevs.push(c);
+            }
+        }
+        self.update_x();
+    }
+
+    /// Return the number of characters before the cursor.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.prevs.len()
+    }
+
     /// Refresh the cache and get it.
     pub fn as_string(&mut self) -> &term::String {
         if self.dirty {
@@ -59,7 +129,7 @@ impl Line {
         while self.x < x {
             if let Some(c) = iter.next() {
                 self.prevs.push(c.clone());
-                self.x += c.width();
+                self.x = util::advance(self.x, c.chr, self.tab_width);
             } else {
                 break;
             }
@@ -68,6 +138,7 @@ impl Line {
             self.nexts.push(c.clone());
         }
         self.dirty = false;
+        self.snap();
         res
     }
 
@@ -79,7 +150,8 @@ impl Line {
         mem::replace(&mut self.prevs, term::String::new())
     }
 
-    /// Set the cursor position by the given x coordinate.
+    /// Set the cursor position by the given character index.
+    /// The cursor never splits a grapheme cluster.
     pub fn set_cursor(&mut self, x: usize) {
         while self.prevs.len() > x {
             self.move_left();
@@ -97,38 +169,51 @@ impl Line {
     /// Insert a char.
     pub fn insert(&mut self, c: char) {
         self.dirty = true;
-        self.x += util::term_width(c);
-        self.prevs.push(
-            term::Char::new(c, term::Brush::black_and_white()),
-        );
+        self.x = util::advance(self.x, c, self.tab_width);
+        let mut chr = term::Char::new(c, term::Brush::black_and_white());
+        chr.literal = self.in_literal();
+        self.prevs.push(chr);
+    }
+
+    /// True iff the cursor is in a string or a comment, judged by the neighbours.
+    /// A quote just before the end of the line closes its string.
+    fn in_literal(&self) -> bool {
+        match (self.prevs.iter().next_back(), self.nexts.iter().next_back()) {
+            (Some(prev), Some(next)) => prev.literal && next.literal,
+            (Some(prev), None) => prev.literal && !"\"'`".contains(prev.chr),
+            _ => false,
+        }
     }
 
     /// Append string before cursor.
     pub fn push_before(&mut self, mut value: term::String) {
-        self.x += value.iter().map(|c| c.width()).sum();
+        self.dirty = true;
         self.prevs.push_string(&mut value);
+        self.update_x();
     }
 
-    /// Move cursor left by 1 character.
+    /// Move cursor left by 1 grapheme cluster.
     pub fn move_left(&mut self) -> bool {
-        if let Some(c) = self.prevs.pop() {
-            self.x -= c.width();
-            self.nexts.push(c);
-            true
-        } else {
-            false
+        let n = self.prev_cluster();
+        for _ in 0..n {
+            if let Some(c) = self.prevs.pop() {
+                self.nexts.push(c);
+            }
         }
+        self.update_x();
+        n > 0
     }
 
-    /// Move cursor right by 1 character.
+    /// Move cursor right by 1 grapheme cluster.
     pub fn move_right(&mut self) -> bool {
-        if let Some(c) = self.nexts.pop() {
-            self.x += c.width();
-            self.prevs.push(c);
-            true
-        } else {
-            false
+        let n = self.next_cluster();
+        for _ in 0..n {
+            if let Some(c) = self.nexts.pop() {
+                self.x = util::advance(self.x, c.chr, self.tab_width);
+                self.prevs.push(c);
+            }
         }
+        n > 0
     }
 
     /// Prepend a line to this.
@@ -136,12 +221,15 @@ impl Line {
         let mut target = mem::replace(&mut self.prevs, target);
         self.prevs.push_string(&mut target);
         self.dirty = true;
-        self.x = self.prevs.iter().map(|c| c.width()).sum()
+        self.update_x();
     }
 
     /// Append a line to this.
     pub fn append(&mut self, target: term::String) {
-        self.nexts.push_string(&mut target.reversed());
+        let mut nexts = target.reversed();
+        nexts.push_string(&mut self.nexts);
+        self.nexts = nexts;
+        self.dirty = true;
     }
 
     /// Move to the begining of the line.
@@ -157,24 +245,39 @@ impl Line {
     #[inline]
     pub fn move_end(&mut self) {
         while let Some(c) = self.nexts.pop() {
-            self.x += c.width();
+            self.x = util::advance(self.x, c.chr, self.tab_width);
             self.prevs.push(c);
         }
     }
 
-    /// Delete single character before cursor.
+    /// Delete the grapheme cluster before cursor, and return it.
     #[inline]
-    pub fn backspace(&mut self) -> bool {
-        self.prevs.pop().is_some()
+    pub fn backspace(&mut self) -> Option<String> {
+        let n = self.prev_cluster();
+        if n == 0 {
+            return None;
+        }
+        let mut res = vec![];
+        for _ in 0..n {
+            if let Some(c) = self.prevs.pop() {
+                res.push(c.chr);
+            }
+        }
+        self.update_x();
+        self.dirty = true;
+        Some(res.into_iter().rev().collect())
     }
 
-    /// Delete every characters after cursor. Return true iff there is any deleted character.
+    /// Delete every characters after cursor, and return them if any.
     #[inline]
-    pub fn kill(&mut self) -> bool {
-        let res = !self.nexts.is_empty();
+    pub fn kill(&mut self) -> Option<String> {
+        if self.nexts.is_empty() {
+            return None;
+        }
+        let res = self.nexts.reversed().to_str();
         self.nexts.clear();
         self.dirty = true;
-        res
+        Some(res)
     }
 
     /// Convert to a string.
@@ -186,3 +289,37 @@ impl Line {
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_line(s: &str) -> Line {
+        Line::new_from_string(term::String::from_std(s, term::Brush::black_and_white()))
+    }
+
+    #[test]
+    fn test_mixed_width() {
+        let mut line = new_line("a한e\u{301}😀b");
+        assert_eq!(line.x(), 7);
+        line.move_begin();
+        line.move_right();
+        line.move_right();
+        assert_eq!((line.index(), line.x()), (2, 3));
+        // The combining character moves together.
+        line.move_right();
+        assert_eq!((line.index(), line.x()), (4, 4));
+        line.move_right();
+        assert_eq!((line.index(), line.x()), (5, 6));
+        line.set_cursor(3);
+        assert_eq!((line.index(), line.x()), (4, 4));
+        line.move_left();
+        assert_eq!((line.index(), line.x()), (2, 3));
+        line.move_end();
+        assert_eq!(line.backspace(), Some(String::from("b")));
+        assert_eq!(line.backspace(), Some(String::from("😀")));
+        assert_eq!(line.backspace(), Some(String::from("e\u{301}")));
+        assert_eq!(line.to_str(), "a한");
+        assert_eq!(line.x(), 3);
+    }
+}
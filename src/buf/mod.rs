@@ -1,5 +1,19 @@
 mod line;
 mod buffer;
+mod stamp;
+mod encoding;
+mod search;
+mod word;
+mod indent;
+mod comment;
+mod highlight;
 
 pub use self::buffer::{Buffer, BackspaceRes, KillLineRes};
 pub use self::line::Line;
+pub use self::stamp::Stamp;
+pub use self::encoding::{Encoding, LineEnding, Format};
+pub use self::search::{Range, SearchOptions, Pattern, Match};
+pub use self::word::capitalize;
+pub use self::indent::IndentRules;
+pub use self::comment::CommentMarkers;
+pub use self::highlight::LineHighlighter;
@@ -0,0 +1,374 @@
+use regex::{self, Regex, RegexBuilder};
+use hq;
+
+/// A region of the buffer. Positions are (character index, line), and `end` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub start: hq::Pair,
+    pub end: hq::Pair,
+}
+
+/// How the pattern is matched.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    pub regex: bool,
+    /// Ignore the case unless the pattern has an uppercase letter.
+    pub smart_case: bool,
+    pub whole_word: bool,
+}
+
+/// The compiled pattern. Literals are escaped into the regular expression.
+pub struct Pattern {
+    re: Regex,
+    options: SearchOptions,
+}
+
+/// A match with its capture groups: (name, text).
+#[derive(Debug)]
+pub struct Match {
+    pub range: Range,
+    groups: Vec<(Option<String>, Option<String>)>,
+}
+
+/// The lines joined by newlines, so that a pattern can span line breaks.
+struct Text {
+    s: String,
+    starts: Vec<usize>,
+}
+
+/// Convert the byte offset of the line into the character index.
+#[inline]
+fn char_index(line: &str, byte: usize) -> usize {
+    line[..byte].chars().count()
+}
+
+/// Convert the character index of the line into the byte offset.
+#[inline]
+fn byte_offset(line: &str, index: usize) -> usize {
+    line.char_indices().nth(index).map_or(line.len(), |(b, _)| b)
+}
+
+#[inline]
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// True iff the pattern has an uppercase letter. Escapes like `\W` are not letters.
+fn has_upper(s: &str, is_regex: bool) -> bool {
+    let mut escaped = false;
+    for c in s.chars() {
+        if escaped {
+            escaped = false;
+        } else if is_regex && c == '\\' {
+            escaped = true;
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+impl Range {
+    /// The columns `[from, to)` of the range in the line `y` of length `len`.
+    pub fn columns(&self, y: usize, len: usize) -> Option<(usize, usize)> {
+        if y < self.start.1 || y > self.end.1 {
+            return None;
+        }
+        let from = if y == self.start.1 { self.start.0 } else { 0 };
+        let to = if y == self.end.1 { self.end.0 } else { len };
+        Some((from, to))
+    }
+}
+
+impl Text {
+    fn new(lines: &[String]) -> Text {
+        let mut starts = Vec::with_capacity(lines.len());
+        let mut len = 0;
+        for line in lines {
+            starts.push(len);
+            len += line.len() + 1;
+        }
+        Text {
+            s: lines.join("\n"),
+            starts: starts,
+        }
+    }
+
+    /// Convert the byte offset into the position.
+    fn pos(&self, byte: usize) -> hq::Pair {
+        let y = match self.starts.binary_search(&byte) {
+            Ok(y) => y,
+            Err(y) => y - 1,
+        };
+        (char_index(&self.s[self.starts[y]..], byte - self.starts[y]), y)
+    }
+
+    /// Convert the position into the byte offset.
+    /// A position beyond the end of the line means the beginning of the next line.
+    fn byte(&self, pos: hq::Pair) -> Option<usize> {
+        let start = *self.starts.get(pos.1)?;
+        let end = self.starts.get(pos.1 + 1).map_or(self.s.len(), |&b| b - 1);
+        let line = &self.s[start..end];
+        if pos.0 <= line.chars().count() {
+            Some(start + byte_offset(line, pos.0))
+        } else if pos.1 + 1 < self.starts.len() {
+            Some(end + 1)
+        } else {
+            None
+        }
+    }
+
+    /// The byte offset of the character after `byte`.
+    fn next_char(&self, byte: usize) -> usize {
+        self.s[byte..].chars().next().map_or(byte + 1, |c| byte + c.len_utf8())
+    }
+
+    /// True iff `[start, end)` is not adjacent to word characters.
+    fn is_whole_word(&self, start: usize, end: usize) -> bool {
+        !self.s[..start].chars().next_back().map_or(false, is_word) &&
+            !self.s[end..].chars().next().map_or(false, is_word)
+    }
+}
+
+impl Pattern {
+    pub fn new(s: &str, options: SearchOptions) -> Result<Pattern, regex::Error> {
+        let src = if options.regex {
+            String::from(s)
+        } else {
+            regex::escape(s)
+        };
+        let re = RegexBuilder::new(&src)
+            .multi_line(true)
+            .case_insensitive(options.smart_case && !has_upper(s, options.regex))
+            .build()?;
+        Ok(Pattern {
+            re: re,
+            options: options,
+        })
+    }
+
+    /// True iff the pattern is a regular expression, whose replacement may refer groups.
+    #[inline]
+    pub fn is_regex(&self) -> bool {
+        self.options.regex
+    }
+
+    /// Find the first match at or after the byte offset.
+    /// Return the match and the byte offset to continue.
+    fn find_in(&self, text: &Text, mut at: usize) -> Option<(Match, usize)> {
+        if text.starts.is_empty() {
+            return None;
+        }
+        while at <= text.s.len() {
+            let caps = self.re.captures_at(&text.s, at)?;
+            let whole = caps.get(0)?;
+            let (start, end) = (whole.start(), whole.end());
+            if self.options.whole_word && !text.is_whole_word(start, end) {
+                at = text.next_char(start);
+                continue;
+            }
+            let groups = self.re
+                .capture_names()
+                .zip(caps.iter())
+                .map(|(name, m)| {
+                    (name.map(String::from), m.map(|m| String::from(m.as_str())))
+                })
+                .collect();
+            let m = Match {
+                range: Range {
+                    start: text.pos(start),
+                    end: text.pos(end),
+                },
+                groups: groups,
+            };
+            // An empty match must go forward.
+            let next = if start == end { text.next_char(end) } else { end };
+            return Some((m, next));
+        }
+        None
+    }
+
+    /// Find the first match at or after `from`.
+    pub fn find(&self, lines: &[String], from: hq::Pair) -> Option<Match> {
+        let text = Text::new(lines);
+        let at = text.byte(from)?;
+        self.find_in(&text, at).map(|(m, _)| m)
+    }
+
+    /// Find every match in the lines.
+    pub fn find_all(&self, lines: &[String]) -> Vec<Range> {
+        self.matches_from(lines, (0, 0)).into_iter().map(|m| m.range).collect()
+    }
+
+    /// Find every match at or after `from`, in one pass over the lines.
+    pub fn matches_from(&self, lines: &[String], from: hq::Pair) -> Vec<Match> {
+        let text = Text::new(lines);
+        let mut res = vec![];
+        let mut at = match text.byte(from) {
+            Some(at) => at,
+            None => return res,
+        };
+        while let Some((m, next)) = self.find_in(&text, at) {
+            res.push(m);
+            at = next;
+        }
+        res
+    }
+}
+
+impl Match {
+    /// True iff the match is an empty string.
+    pub fn is_empty(&self) -> bool {
+        self.range.start == self.range.end
+    }
+
+    /// Return the text of the group, by the index or the name.
+    fn group(&self, key: &str) -> &str {
+        let found = match key.parse::<usize>() {
+            Ok(i) => self.groups.get(i),
+            Err(_) => self.groups.iter().find(
+                |g| g.0.as_ref().map_or(false, |n| n == key),
+            ),
+        };
+        found.and_then(|g| g.1.as_ref()).map_or("", |s| s.as_str())
+    }
+
+    /// Expand `$N`, `${N}` and `${name}` in the replacement. `$$` is a dollar sign.
+    pub fn expand(&self, replacement: &str) -> String {
+        let mut res = String::new();
+        let mut it = replacement.chars().peekable();
+        while let Some(c) = it.next() {
+            if c != '$' {
+                res.push(c);
+                continue;
+            }
+            let mut key = String::new();
+            match it.peek().cloned() {
+                Some('$') => {
+                    it.next();
+                    res.push('$');
+                    continue;
+                }
+                Some('{') => {
+                    it.next();
+                    while let Some(c) = it.next() {
+                        if c == '}' {
+                            break;
+                        }
+                        key.push(c);
+                    }
+                }
+                _ => {
+                    while let Some(&c) = it.peek() {
+                        if !c.is_digit(10) {
+                            break;
+                        }
+                        key.push(c);
+                        it.next();
+                    }
+                }
+            }
+            if key.is_empty() {
+                res.push('$');
+            } else {
+                res.push_str(self.group(&key));
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(src: &[&str]) -> Vec<String> {
+        src.iter().map(|s| String::from(*s)).collect()
+    }
+
+    fn options(regex: bool, smart_case: bool, whole_word: bool) -> SearchOptions {
+        SearchOptions {
+            regex: regex,
+            smart_case: smart_case,
+            whole_word: whole_word,
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let src = lines(&["foo bar", "한글 bar"]);
+        let pattern = Pattern::new("bar", Default::default()).unwrap();
+        let m = pattern.find(&src, (0, 0)).unwrap();
+        assert_eq!(m.range, Range { start: (4, 0), end: (7, 0) });
+        let m = pattern.find(&src, (5, 0)).unwrap();
+        assert_eq!(m.range, Range { start: (3, 1), end: (6, 1) });
+        assert!(pattern.find(&src, (4, 1)).is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        let src = lines(&["key = value"]);
+        let pattern = Pattern::new(r"(?P<k>\w+) = (\w+)", options(true, false, false)).unwrap();
+        let m = pattern.find(&src, (0, 0)).unwrap();
+        assert_eq!(m.expand("$2: ${k} $$1"), "value: key $1");
+    }
+
+    #[test]
+    fn test_smart_case() {
+        let src = lines(&["Foo foo", "FOO"]);
+        let pattern = Pattern::new("foo", options(false, true, false)).unwrap();
+        assert_eq!(pattern.find_all(&src).len(), 3);
+        let pattern = Pattern::new("Foo", options(false, true, false)).unwrap();
+        assert_eq!(pattern.find_all(&src), vec![Range { start: (0, 0), end: (3, 0) }]);
+        // `\W` is not an uppercase letter.
+        let pattern = Pattern::new(r"o\Wf", options(true, true, false)).unwrap();
+        assert_eq!(
+            pattern.find_all(&src),
+            vec![
+                Range { start: (2, 0), end: (5, 0) },
+                Range { start: (6, 0), end: (1, 1) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let src = lines(&["let a =", "    1;", "let b = 2;"]);
+        let pattern = Pattern::new(r"=\s+1", options(true, false, false)).unwrap();
+        let m = pattern.find(&src, (0, 0)).unwrap();
+        assert_eq!(m.range, Range { start: (6, 0), end: (5, 1) });
+        assert_eq!(m.range.columns(0, 7), Some((6, 7)));
+        assert_eq!(m.range.columns(1, 6), Some((0, 5)));
+        let pattern = Pattern::new(r"^let", options(true, false, false)).unwrap();
+        assert_eq!(
+            pattern.find_all(&src),
+            vec![
+                Range { start: (0, 0), end: (3, 0) },
+                Range { start: (0, 2), end: (3, 2) },
+            ]
+        );
+        // Beyond the end of the line continues from the next line.
+        let m = pattern.find(&src, (8, 1)).unwrap();
+        assert_eq!(m.range.start, (0, 2));
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let src = lines(&["cat concat", "cat_s (cat)"]);
+        let pattern = Pattern::new("cat", options(false, false, true)).unwrap();
+        assert_eq!(
+            pattern.find_all(&src),
+            vec![
+                Range { start: (0, 0), end: (3, 0) },
+                Range { start: (7, 1), end: (10, 1) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_match() {
+        let src = lines(&["ab", ""]);
+        let pattern = Pattern::new("x*", options(true, false, false)).unwrap();
+        assert_eq!(pattern.find_all(&src).len(), 4);
+    }
+}
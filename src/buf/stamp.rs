@@ -0,0 +1,62 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The state of a file on the disk, to notice changes from outside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stamp {
+    mtime: SystemTime,
+    size: u64,
+    hash: u64,
+}
+
+impl Stamp {
+    /// Read the stamp of the given file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Stamp> {
+        let meta = fs::metadata(&path)?;
+        let mut content = vec![];
+        fs::File::open(&path)?.read_to_end(&mut content)?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(Stamp {
+            mtime: meta.modified()?,
+            size: meta.len(),
+            hash: hasher.finish(),
+        })
+    }
+
+    /// Update the stamp from the file. Return true iff the content has been changed.
+    /// The content is read only when the mtime or the size differs.
+    pub fn update<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        let meta = fs::metadata(&path)?;
+        if meta.modified()? == self.mtime && meta.len() == self.size {
+            return Ok(false);
+        }
+        let stamp = Stamp::from_file(path)?;
+        let res = stamp.hash != self.hash;
+        *self = stamp;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Write;
+
+    #[test]
+    fn test_update() {
+        let path = env::temp_dir().join("thief_test_stamp");
+        fs::File::create(&path).unwrap().write_all(b"hello").unwrap();
+        let mut stamp = Stamp::from_file(&path).unwrap();
+        assert!(!stamp.update(&path).unwrap());
+        fs::File::create(&path).unwrap().write_all(b"hello, world").unwrap();
+        assert!(stamp.update(&path).unwrap());
+        assert!(!stamp.update(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Characters joining words by the syntax, in addition to Unicode word segmentation.
+pub fn word_chars(syntax: &str) -> &'static str {
+    match syntax {
+        "CSS" | "HTML" | "Lisp" | "Clojure" | "Makefile" | "YAML" => "-",
+        _ => "",
+    }
+}
+
+/// The words of the line as ranges `[start, end)` of character indices.
+/// Adjacent words are merged when they are joined by `extra` characters,
+/// which are not words by themselves.
+pub fn words(line: &str, extra: &str) -> Vec<(usize, usize)> {
+    // The segments as (start, end, is_word), where None means joining characters.
+    let mut segs: Vec<(usize, usize, Option<bool>)> = vec![];
+    let mut idx = 0;
+    for seg in line.split_word_bounds() {
+        let len = seg.chars().count();
+        let kind = if seg.chars().any(|c| c.is_alphanumeric()) {
+            Some(true)
+        } else if !extra.is_empty() && seg.chars().all(|c| extra.contains(c)) {
+            None
+        } else {
+            Some(false)
+        };
+        segs.push((idx, idx + len, kind));
+        idx += len;
+    }
+    // A run of joining characters counts only between words.
+    let is_word_seg = |seg: &(usize, usize, Option<bool>)| seg.2 == Some(true);
+    let mut res: Vec<(usize, usize)> = vec![];
+    for (i, &(start, end, kind)) in segs.iter().enumerate() {
+        let is_word = kind.unwrap_or_else(|| {
+            let before = segs[..i].iter().rev().find(|seg| seg.2.is_some());
+            let after = segs[i + 1..].iter().find(|seg| seg.2.is_some());
+            before.map_or(false, &is_word_seg) && after.map_or(false, &is_word_seg)
+        });
+        if is_word {
+            match res.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => res.push((start, end)),
+            }
+        }
+    }
+    res
+}
+
+/// Uppercase the first letter and lowercase the others.
+pub fn capitalize(s: &str) -> String {
+    let mut res = String::new();
+    let mut first = true;
+    for c in s.chars() {
+        if first && c.is_alphanumeric() {
+            first = false;
+            res.extend(c.to_uppercase());
+        } else {
+            res.extend(c.to_lowercase());
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("foo_bar, baz", ""), vec![(0, 7), (9, 12)]);
+        assert_eq!(words("한글 단어", ""), vec![(0, 2), (3, 5)]);
+        assert_eq!(words("margin-top: 0", ""), vec![(0, 6), (7, 10), (12, 13)]);
+        assert_eq!(words("margin-top: 0", "-"), vec![(0, 10), (12, 13)]);
+        assert_eq!(words("a - b--c -", "-"), vec![(0, 1), (4, 8)]);
+    }
+
+    #[test]
+    fn test_capitalize() {
+        assert_eq!(capitalize("  hELLO"), "  Hello");
+    }
+}
@@ -1,22 +1,43 @@
 use hq::{Arg, Func};
+use ui;
+
+/// What a command does.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Func(Func),
+    /// Run the operation on the focused component.
+    Operation(ui::Operation),
+}
 
-#[allow(dead_code)]
 pub struct Command {
     name: String,
+    /// The description shown by `describe-command`.
+    doc: String,
     args: Vec<Arg>,
-    pub func: Func,
+    pub action: Action,
 }
 
 impl Command {
     /// Create a new command. It must be done at the initialization phase.
-    pub fn new(name: &str, args: Vec<Arg>, func: Func) -> Command {
+    pub fn new(name: &str, doc: &str, args: Vec<Arg>, action: Action) -> Command {
         Command {
             name: String::from(name),
+            doc: String::from(doc),
             args: args,
-            func: func,
+            action: action,
         }
     }
 
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn doc(&self) -> &str {
+        &self.doc
+    }
+
     pub fn args_len(&self) -> usize {
         self.args.len()
     }
@@ -1,11 +1,13 @@
 mod command;
 
 use hq::{Arg, Func};
+use ui;
 use std::collections::BTreeMap;
-pub use self::command::Command;
+pub use self::command::{Action, Command};
 
 pub enum Response {
     Func(Func, Vec<String>),
+    Operation(ui::Operation),
     Require(Arg),
     Message(String),
 }
@@ -25,11 +27,48 @@ impl Commands {
         }
     }
 
-    pub fn add(&mut self, name: &str, args: Vec<Arg>, func: Func) {
-        let res = Command::new(name, args, func);
+    pub fn add(&mut self, name: &str, doc: &str, args: Vec<Arg>, func: Func) {
+        let res = Command::new(name, doc, args, Action::Func(func));
         self.commands.insert(String::from(name), res);
     }
 
+    /// Add a command running the operation on the focused component.
+    pub fn add_operation(&mut self, name: &str, doc: &str, op: ui::Operation) {
+        let res = Command::new(name, doc, vec![], Action::Operation(op));
+        self.commands.insert(String::from(name), res);
+    }
+
+    /// True iff a command is waiting for its arguments.
+    pub fn pending(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Forget the command waiting for its arguments.
+    pub fn cancel(&mut self) {
+        self.name = None;
+        self.args.clear();
+    }
+
+    /// The argument the pending command waits for.
+    pub fn next_arg(&self) -> Option<Arg> {
+        let name = self.name.as_ref()?;
+        let cmd = self.commands.get(name)?;
+        if self.args.len() < cmd.args_len() {
+            Some(cmd.arg(self.args.len()))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// The names of the commands in order.
+    pub fn names(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+
     /// Receive a function name or argument.
     pub fn query(&mut self, command: &str) -> Response {
         if self.name.is_some() {
@@ -44,7 +83,10 @@ impl Commands {
                 if cmd.args_len() == self.args.len() {
                     let mut res = vec![];
                     res.append(&mut self.args);
-                    return Response::Func(cmd.func, res);
+                    return match cmd.action {
+                        Action::Func(func) => Response::Func(func, res),
+                        Action::Operation(op) => Response::Operation(op),
+                    };
                 } else {
                     // TODO: Find a clear way
                     self.name = Some(name);
@@ -55,3 +97,49 @@ impl Commands {
         Response::Message(String::from("Internal error."))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ui;
+    use hq::Workspace;
+    use hq::enums::ArgKind;
+    use util::ResultBox;
+
+    fn nop(_: &mut Workspace, _: Vec<String>) -> ResultBox<ui::Request> {
+        Ok(ui::Request::Quit)
+    }
+
+    #[test]
+    fn test_next_arg() {
+        let mut commands = Commands::new();
+        commands.add("quit", "Quit.", vec![], nop);
+        commands.add(
+            "set-tab-width",
+            "Set the tab width.",
+            vec![Arg::integer("Tab width: ")],
+            nop,
+        );
+        assert_eq!(commands.names(), vec!["quit", "set-tab-width"]);
+        assert!(commands.next_arg().is_none());
+        match commands.query("set-tab-width") {
+            Response::Require(arg) => assert_eq!(arg.prompt, "Tab width: "),
+            _ => panic!("no argument required"),
+        }
+        assert_eq!(commands.next_arg().unwrap().kind, ArgKind::Integer);
+        match commands.query("4") {
+            Response::Func(_, args) => assert_eq!(args, vec!["4"]),
+            _ => panic!("not called"),
+        }
+        assert!(!commands.pending());
+        commands.query("set-tab-width");
+        commands.cancel();
+        assert!(!commands.pending());
+        assert!(commands.next_arg().is_none());
+        commands.add_operation("forward-char", "Move forward.", ui::Operation::ForwardChar);
+        match commands.query("forward-char") {
+            Response::Operation(op) => assert_eq!(op, ui::Operation::ForwardChar),
+            _ => panic!("no operation"),
+        }
+    }
+}
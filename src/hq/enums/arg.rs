@@ -0,0 +1,161 @@
+use hq::History;
+use hq::keymap;
+
+/// The kind of an argument, which decides the validation and the completion.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgKind {
+    Path,
+    Text,
+    Integer,
+    YesNo,
+    /// The name of an existing buffer.
+    Buffer,
+    /// The name of a command.
+    Command,
+    /// A key sequence, e.g. `C-x C-f`.
+    Keys,
+    /// One of the fixed choices.
+    Choice(Vec<String>),
+}
+
+/// An argument of a command, asked in the command bar before the command runs.
+#[derive(Clone, Debug)]
+pub struct Arg {
+    pub kind: ArgKind,
+    pub prompt: String,
+    /// Taken when the input is empty.
+    pub default: Option<String>,
+    pub history: Option<History>,
+}
+
+impl Arg {
+    fn new(kind: ArgKind, prompt: &str, history: Option<History>) -> Arg {
+        Arg {
+            kind: kind,
+            prompt: String::from(prompt),
+            default: None,
+            history: history,
+        }
+    }
+
+    pub fn path(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Path, prompt, Some(History::Path))
+    }
+
+    pub fn text(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Text, prompt, Some(History::Text))
+    }
+
+    pub fn integer(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Integer, prompt, Some(History::Text))
+    }
+
+    pub fn yes_no(prompt: &str) -> Arg {
+        Arg::new(ArgKind::YesNo, prompt, None)
+    }
+
+    pub fn buffer(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Buffer, prompt, None)
+    }
+
+    pub fn command(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Command, prompt, Some(History::Command))
+    }
+
+    pub fn keys(prompt: &str) -> Arg {
+        Arg::new(ArgKind::Keys, prompt, None)
+    }
+
+    pub fn choice(prompt: &str, choices: &[&str]) -> Arg {
+        let choices = choices.iter().map(|s| String::from(*s)).collect();
+        Arg::new(ArgKind::Choice(choices), prompt, None)
+    }
+
+    pub fn with_default(self, value: &str) -> Arg {
+        Arg {
+            default: Some(String::from(value)),
+            ..self
+        }
+    }
+
+    pub fn with_history(self, history: History) -> Arg {
+        Arg {
+            history: Some(history),
+            ..self
+        }
+    }
+
+    /// The prompt with the default value, e.g. `Tab width (default 8): `.
+    pub fn message(&self) -> String {
+        match self.default {
+            Some(ref value) => {
+                let prompt = self.prompt.trim_right();
+                let (head, tail) = if prompt.ends_with(':') {
+                    (&prompt[..prompt.len() - 1], ":")
+                } else {
+                    (prompt, "")
+                };
+                format!("{} (default {}){} ", head, value, tail)
+            }
+            None => self.prompt.clone(),
+        }
+    }
+
+    /// The fixed candidates of the input, besides the buffer and the command names.
+    pub fn choices(&self) -> Vec<String> {
+        match self.kind {
+            ArgKind::YesNo => vec![String::from("yes"), String::from("no")],
+            ArgKind::Choice(ref choices) => choices.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// Check the input against the kind, given the candidates to be completed.
+    pub fn validate(&self, input: &str, candidates: &[String]) -> Result<(), String> {
+        let input = input.trim();
+        match self.kind {
+            ArgKind::Path | ArgKind::Text => Ok(()),
+            ArgKind::Integer => {
+                input.parse::<i64>().map(|_| ()).map_err(
+                    |_| String::from("Expected an integer."),
+                )
+            }
+            ArgKind::Keys if keymap::parse_keys(input).is_some() => Ok(()),
+            ArgKind::Keys => Err(String::from("Invalid key sequence.")),
+            ArgKind::YesNo if input == "yes" || input == "no" => Ok(()),
+            ArgKind::YesNo => Err(String::from("Please answer yes or no.")),
+            _ if candidates.iter().any(|s| s == input) => Ok(()),
+            ArgKind::Buffer => Err(String::from("No such buffer.")),
+            ArgKind::Command => Err(String::from("No such command.")),
+            ArgKind::Choice(ref choices) => Err(format!("Expected one of {}.", choices.join(", "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let integer = Arg::integer("Tab width: ").with_default("8");
+        assert_eq!(integer.message(), "Tab width (default 8): ");
+        assert!(integer.validate(" 12 ", &[]).is_ok());
+        assert_eq!(integer.validate("x", &[]), Err(String::from("Expected an integer.")));
+        let yes_no = Arg::yes_no("Really? ");
+        assert!(yes_no.validate("yes", &yes_no.choices()).is_ok());
+        assert!(yes_no.validate("y", &yes_no.choices()).is_err());
+        let choice = Arg::choice("Line ending: ", &["lf", "crlf"]);
+        assert!(choice.validate("crlf", &choice.choices()).is_ok());
+        assert_eq!(
+            choice.validate("cr", &choice.choices()),
+            Err(String::from("Expected one of lf, crlf."))
+        );
+        let keys = Arg::keys("Key: ");
+        assert!(keys.validate("C-x C-f", &[]).is_ok());
+        assert!(keys.validate("C-", &[]).is_err());
+        let buffer = Arg::buffer("Buffer: ");
+        assert!(buffer.validate("a.rs", &[String::from("a.rs")]).is_ok());
+        assert!(buffer.validate("b.rs", &[String::from("a.rs")]).is_err());
+    }
+}
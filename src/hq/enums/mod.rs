@@ -1,3 +1,4 @@
+mod arg;
 mod request;
 mod response;
 
@@ -5,14 +6,9 @@ use util::ResultBox;
 use ui;
 use hq::workspace::Workspace;
 
+pub use self::arg::{Arg, ArgKind};
 pub use self::request::Request;
 pub use self::response::Response;
 
 pub type Func = fn(&mut Workspace, Vec<String>) -> ResultBox<ui::Request>;
 pub type Pair = (usize, usize);
-
-#[derive(Clone)]
-pub enum Arg {
-    Path(String),
-//    String(String),
-}
@@ -32,7 +32,9 @@ impl Request {
     }
 
     pub fn from_char(c: char) -> Request {
-        Request::Keyboard(if c as u32 <= 26 {
+        Request::Keyboard(if c == '\0' {
+            term::Key::Ctrl('@')
+        } else if c as u32 <= 26 {
             term::Key::Ctrl((c as u8 + b'a' - 1) as char)
         } else {
             term::Key::Char(c)
@@ -45,7 +47,8 @@ impl Request {
             Some('\x1b') => {
                 match it.next() {
                     Some('[') => process_csi(&mut it),
-                    _ => None,
+                    Some(c) => Some(Request::Keyboard(term::Key::Meta(c))),
+                    None => Some(Request::Keyboard(term::Key::Esc)),
                 }
             }
             Some(c) => Some(Request::from_char(c)),
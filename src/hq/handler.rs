@@ -1,59 +1,806 @@
-use util::ResultBox;
-use ui::{self, Component};
+use util::{self, ResultBox};
+use ui::{self, Component, Operation};
 use term;
 
 use hq;
-use hq::enums::Arg;
+use hq::enums::{Arg, ArgKind};
 use hq::commands::{self, Commands};
-use hq::shortcut::Shortcut;
+use hq::keymap::{self, Layer, keys_to_string};
+use hq::help::{self, Help};
+use hq::mode;
+use hq::prefix::Prefix;
 use hq::workspace::Workspace;
 
+/// Check files on the disk every second. (A tick is 100ms.)
+const CHECK_FILES_TICKS: usize = 10;
+/// Write the recovery files every 30 seconds.
+const AUTO_SAVE_TICKS: usize = 300;
+/// Milliseconds of a tick.
+const TICK_MS: usize = 100;
+
+/// The commands run by the focused component: the editor, or the command bar.
+const OPERATION_COMMANDS: &'static [(&'static str, Operation, &'static str)] = &[
+    ("execute-command", Operation::ExecuteCommand, "Type a command name in the command bar."),
+    ("toggle-split", Operation::ToggleSplit, "Change the number of the editor windows."),
+    (
+        "beginning-of-line",
+        Operation::BeginningOfLine,
+        "Move the cursor to the beginning of the line.",
+    ),
+    ("end-of-line", Operation::EndOfLine, "Move the cursor to the end of the line."),
+    ("newline", Operation::Newline, "Break the line, indenting the new line if auto-indent is on."),
+    (
+        "delete-backward-char",
+        Operation::DeleteBackwardChar,
+        "Delete the character before the cursor.",
+    ),
+    (
+        "kill-line",
+        Operation::KillLine,
+        "Kill the rest of the line, or as many lines as the prefix argument.",
+    ),
+    ("next-line", Operation::NextLine, "Move the cursor to the next line."),
+    ("previous-line", Operation::PreviousLine, "Move the cursor to the previous line."),
+    ("forward-char", Operation::ForwardChar, "Move the cursor forward."),
+    ("backward-char", Operation::BackwardChar, "Move the cursor backward."),
+    ("forward-word", Operation::ForwardWord, "Move the cursor to the end of the word."),
+    ("backward-word", Operation::BackwardWord, "Move the cursor to the beginning of the word."),
+    ("kill-word", Operation::KillWord, "Kill the characters to the end of the word."),
+    (
+        "backward-kill-word",
+        Operation::BackwardKillWord,
+        "Kill the characters to the beginning of the word.",
+    ),
+    ("transpose-chars", Operation::TransposeChars, "Swap the characters around the cursor."),
+    ("transpose-words", Operation::TransposeWords, "Swap the words around the cursor."),
+    ("upcase-word", Operation::UpcaseWord, "Convert the word to upper case."),
+    ("downcase-word", Operation::DowncaseWord, "Convert the word to lower case."),
+    ("capitalize-word", Operation::CapitalizeWord, "Capitalize the word."),
+    (
+        "indent-for-tab",
+        Operation::IndentForTab,
+        "Insert a tab, or the spaces up to the next tab stop.",
+    ),
+    (
+        "beginning-of-buffer",
+        Operation::BeginningOfBuffer,
+        "Move the cursor to the beginning of the buffer.",
+    ),
+    ("end-of-buffer", Operation::EndOfBuffer, "Move the cursor to the end of the buffer."),
+    ("scroll-up", Operation::ScrollUp, "Scroll the editor a page down."),
+    ("scroll-down", Operation::ScrollDown, "Scroll the editor a page up."),
+    (
+        "recenter",
+        Operation::Recenter,
+        "Scroll the editor so that the cursor is at the center, top or bottom.",
+    ),
+    ("exit-prompt", Operation::ExitPrompt, "Finish the input of the command bar."),
+    ("previous-history", Operation::PreviousHistory, "Show the previous entry of the history."),
+    ("next-history", Operation::NextHistory, "Show the next entry of the history."),
+    ("history-search", Operation::HistorySearch, "Search the history incrementally."),
+    (
+        "complete",
+        Operation::Complete,
+        "Complete the input to the longest common prefix of the candidates.",
+    ),
+];
+
+/// The default bindings of the global keymap.
+const GLOBAL_KEYS: &'static [(&'static str, &'static str)] = &[
+    ("C-a", "beginning-of-line"),
+    ("<home>", "beginning-of-line"),
+    ("C-e", "end-of-line"),
+    ("<end>", "end-of-line"),
+    ("RET", "newline"),
+    ("C-j", "newline-and-indent"),
+    ("DEL", "delete-backward-char"),
+    ("C-k", "kill-line"),
+    ("C-n", "next-line"),
+    ("<down>", "next-line"),
+    ("C-p", "previous-line"),
+    ("<up>", "previous-line"),
+    ("C-f", "forward-char"),
+    ("<right>", "forward-char"),
+    ("C-b", "backward-char"),
+    ("<left>", "backward-char"),
+    ("M-f", "forward-word"),
+    ("M-b", "backward-word"),
+    ("M-d", "kill-word"),
+    ("M-DEL", "backward-kill-word"),
+    ("C-t", "transpose-chars"),
+    ("M-t", "transpose-words"),
+    ("M-u", "upcase-word"),
+    ("M-l", "downcase-word"),
+    ("M-c", "capitalize-word"),
+    ("TAB", "indent-for-tab"),
+    ("M-<", "beginning-of-buffer"),
+    ("M->", "end-of-buffer"),
+    ("C-v", "scroll-up"),
+    ("M-v", "scroll-down"),
+    ("C-l", "recenter"),
+    ("C-x C-f", "find-file"),
+    ("C-x b", "switch-to-buffer"),
+    ("C-h k", "describe-key"),
+    ("C-h f", "describe-command"),
+    ("C-h b", "list-keybindings"),
+    ("C-x C-c", "quit"),
+    ("C-x (", "start-macro"),
+    ("C-x )", "end-macro"),
+    ("C-x e", "call-macro"),
+    ("C-x C-s", "save-buffer"),
+    ("C-@", "set-mark"),
+    ("C-c %", "jump-to-matching-bracket"),
+    ("M-;", "comment-region"),
+    ("C-c >", "indent-region"),
+    ("C-c <", "outdent-region"),
+    ("M-g g", "goto-line"),
+    ("C-s", "search-forward"),
+    ("M-s", "search-regexp"),
+    ("M-%", "query-replace"),
+    ("C-x u", "undo"),
+    ("C-_", "undo"),
+    ("C-x C-q", "toggle-read-only"),
+    ("C-g", "keyboard-quit"),
+];
+
+/// The default bindings of the window keymap.
+const WINDOW_KEYS: &'static [(&'static str, &'static str)] = &[
+    ("M-x", "execute-command"),
+    ("C-d", "toggle-split"),
+];
+
+/// The default bindings of the prompt keymap.
+const PROMPT_KEYS: &'static [(&'static str, &'static str)] = &[
+    ("RET", "exit-prompt"),
+    ("C-j", "exit-prompt"),
+    ("M-p", "previous-history"),
+    ("<up>", "previous-history"),
+    ("M-n", "next-history"),
+    ("<down>", "next-history"),
+    ("C-r", "history-search"),
+    ("TAB", "complete"),
+];
+
 /// `io::Handler` -- `Request` -->
 ///              <-- `Response`--  `hq::Handler`
 pub struct Handler {
     screen: ui::Screen,
     workspace: Workspace,
     commands: Commands,
-    shortcut: Shortcut,
+    prefix: Option<Prefix>,
+    /// The keys typed for `describe-key`, while waiting for the command.
+    describing: Option<Vec<term::Key>>,
+    /// The ticks since the last key of the sequence, until the hints are shown.
+    hint_ticks: Option<usize>,
+    /// True iff the hints of the key sequence are shown.
+    hinting: bool,
+    ticks: usize,
 }
 
 impl Handler {
     /// Initialize.
     pub fn new(screen: ui::Screen) -> ResultBox<Handler> {
         let mut commands = Commands::new();
-        let mut shortcut = Shortcut::new();
         commands.add(
             "find-file",
-            vec![Arg::Path(String::from("filename"))],
+            "Open a file into a new buffer, choosing it in the navigator.",
+            vec![Arg::path("filename")],
             Workspace::find_file,
         );
-        commands.add("quit", vec![], Workspace::quit);
-        shortcut.add(
-            "find-file",
-            vec![term::Key::Ctrl('x'), term::Key::Ctrl('f')],
+        commands.add(
+            "switch-to-buffer",
+            "Show an open buffer in the editor.",
+            vec![Arg::buffer("Switch to buffer: ")],
+            Workspace::switch_to_buffer,
+        );
+        commands.add(
+            keymap::QUIT,
+            "Cancel every pending input, the replace session, the macro being recorded, \
+             the highlights and the mark.",
+            vec![],
+            Workspace::keyboard_quit,
+        );
+        commands.add(
+            "describe-key",
+            "Describe the command run by the key sequence typed next.",
+            vec![],
+            Workspace::describe_key,
+        );
+        commands.add(
+            "describe-command",
+            "Describe a command with the keys bound to it.",
+            vec![Arg::command("Describe command: ")],
+            Workspace::describe_command,
+        );
+        commands.add(
+            "list-keybindings",
+            "List every key sequence with the command bound to it.",
+            vec![],
+            Workspace::list_keybindings,
+        );
+        commands.add(
+            "set-which-key-delay",
+            "Set the milliseconds before the keys continuing a key sequence are shown.",
+            vec![Arg::integer("Key hint delay in ms: ")],
+            Workspace::set_which_key_delay,
+        );
+        commands.add(
+            "global-set-key",
+            "Bind a key sequence to a command in the global keymap.",
+            vec![
+                Arg::keys("Set key globally: "),
+                Arg::command("Set key to command: "),
+            ],
+            Workspace::global_set_key,
+        );
+        commands.add(
+            "global-unset-key",
+            "Remove the binding of a key sequence from the global keymap.",
+            vec![Arg::keys("Unset key globally: ")],
+            Workspace::global_unset_key,
+        );
+        commands.add(
+            "quit",
+            "Quit the editor.",
+            vec![],
+            Workspace::quit,
+        );
+        commands.add(
+            "start-macro",
+            "Start recording a keyboard macro.",
+            vec![],
+            Workspace::start_macro,
+        );
+        commands.add(
+            "end-macro",
+            "Finish recording the keyboard macro.",
+            vec![],
+            Workspace::end_macro,
+        );
+        commands.add(
+            "call-macro",
+            "Replay the last keyboard macro, as many times as the prefix argument.",
+            vec![],
+            Workspace::call_macro,
+        );
+        commands.add(
+            "repeat-macro",
+            "Replay the last keyboard macro the given number of times.",
+            vec![Arg::integer("Repeat count: ")],
+            Workspace::repeat_macro,
+        );
+        commands.add(
+            "name-last-macro",
+            "Give a name to the last keyboard macro and save it.",
+            vec![Arg::text("Name for last macro: ")],
+            Workspace::name_last_macro,
+        );
+        commands.add(
+            "call-named-macro",
+            "Replay a named keyboard macro.",
+            vec![Arg::text("Macro name: ")],
+            Workspace::call_named_macro,
+        );
+        commands.add(
+            "toggle-read-only",
+            "Toggle whether the buffer can be modified.",
+            vec![],
+            Workspace::toggle_read_only,
+        );
+        commands.add(
+            "save-buffer",
+            "Save the buffer into its file.",
+            vec![],
+            Workspace::save_buffer,
+        );
+        commands.add(
+            "save-buffer-creating-directories",
+            "Save the buffer, creating the missing directories.",
+            vec![
+                Arg::yes_no(
+                    "The directory does not exist; create it? (yes or no) ",
+                ),
+            ],
+            Workspace::save_buffer_creating_directories,
+        );
+        commands.add(
+            "revert-buffer",
+            "Discard the changes and reload the buffer from its file.",
+            vec![Arg::yes_no("Revert buffer from file? (yes or no) ")],
+            Workspace::revert_buffer,
         );
-        shortcut.add("quit", vec![term::Key::Ctrl('x'), term::Key::Ctrl('c')]);
+        commands.add(
+            "set-encoding",
+            "Set the encoding the buffer is saved in.",
+            vec![
+                Arg::choice(
+                    "Encoding: ",
+                    &["utf-8", "utf-8-bom", "utf-16le", "utf-16be", "latin-1"],
+                ),
+            ],
+            Workspace::set_encoding,
+        );
+        commands.add(
+            "set-line-ending",
+            "Set the line ending the buffer is saved with.",
+            vec![Arg::choice("Line ending: ", &["lf", "crlf", "cr"])],
+            Workspace::set_line_ending,
+        );
+        commands.add(
+            "set-mode",
+            "Set the major mode of the buffer, overriding the one chosen by the file type.",
+            vec![Arg::choice("Mode: ", &mode::names())],
+            Workspace::set_mode,
+        );
+        commands.add(
+            "set-tab-width",
+            "Set the width of a tab in the buffer.",
+            vec![
+                Arg::integer("Tab width: ").with_default(
+                    &util::DEFAULT_TAB_WIDTH.to_string(),
+                ),
+            ],
+            Workspace::set_tab_width,
+        );
+        commands.add(
+            "toggle-indent-tabs",
+            "Toggle whether the buffer is indented with tabs.",
+            vec![],
+            Workspace::toggle_indent_tabs,
+        );
+        commands.add(
+            "toggle-auto-indent",
+            "Toggle the automatic indentation of new lines.",
+            vec![],
+            Workspace::toggle_auto_indent,
+        );
+        commands.add(
+            "newline-and-indent",
+            "Insert a newline and indent the new line.",
+            vec![],
+            Workspace::newline_and_indent,
+        );
+        commands.add(
+            "set-mark",
+            "Set the mark at the cursor to start a region.",
+            vec![],
+            Workspace::set_mark,
+        );
+        commands.add(
+            "jump-to-matching-bracket",
+            "Move the cursor to the bracket paired with the one at the cursor.",
+            vec![],
+            Workspace::jump_to_matching_bracket,
+        );
+        commands.add(
+            "comment-line",
+            "Comment or uncomment the current line.",
+            vec![],
+            Workspace::comment_line,
+        );
+        commands.add(
+            "comment-region",
+            "Comment or uncomment the lines of the region.",
+            vec![],
+            Workspace::comment_region,
+        );
+        commands.add(
+            "indent-region",
+            "Indent the lines of the region.",
+            vec![],
+            Workspace::indent_region,
+        );
+        commands.add(
+            "outdent-region",
+            "Outdent the lines of the region.",
+            vec![],
+            Workspace::outdent_region,
+        );
+        commands.add(
+            "goto-line",
+            "Move the cursor to LINE or LINE:COL.",
+            vec![Arg::text("Goto line: ")],
+            Workspace::goto_line,
+        );
+        commands.add(
+            "search-forward",
+            "Search for a string forward.",
+            vec![Arg::text("Search: ").with_history(hq::History::Search)],
+            Workspace::search_forward,
+        );
+        commands.add(
+            "search-regexp",
+            "Search for a regular expression forward.",
+            vec![Arg::text("Search regexp: ").with_history(hq::History::Search)],
+            Workspace::search_regexp,
+        );
+        commands.add(
+            "search-word",
+            "Search for a whole word forward.",
+            vec![Arg::text("Search word: ").with_history(hq::History::Search)],
+            Workspace::search_word,
+        );
+        commands.add(
+            "query-replace",
+            "Replace a string, asking at each match.",
+            vec![
+                Arg::text("Query replace: ").with_history(hq::History::Search),
+                Arg::text("Query replace with: ").with_history(hq::History::Replace),
+            ],
+            Workspace::query_replace,
+        );
+        commands.add(
+            "replace-regexp",
+            "Replace a regular expression, asking at each match.",
+            vec![
+                Arg::text("Replace regexp: ").with_history(hq::History::Search),
+                Arg::text("Replace regexp with: ").with_history(hq::History::Replace),
+            ],
+            Workspace::replace_regexp,
+        );
+        commands.add(
+            "undo",
+            "Undo the last change, as many times as the prefix argument.",
+            vec![],
+            Workspace::undo,
+        );
+        commands.add(
+            "recover-file",
+            "Recover the buffer from its auto-save file.",
+            vec![Arg::yes_no("Recover auto-save file? (yes or no) ")],
+            Workspace::recover_file,
+        );
+        for &(name, op, doc) in OPERATION_COMMANDS {
+            commands.add_operation(name, doc, op);
+        }
+        let mut workspace = Workspace::new()?;
+        {
+            let keymaps = workspace.keymaps();
+            let layers = vec![
+                (Layer::Global, GLOBAL_KEYS),
+                (Layer::Window, WINDOW_KEYS),
+                (Layer::Prompt, PROMPT_KEYS),
+            ];
+            let modes = mode::modes().iter().map(|mode| {
+                (Layer::Mode(String::from(mode.name)), mode.keys)
+            });
+            let layers = layers.into_iter().chain(modes);
+            for (layer, bindings) in layers {
+                for &(keys, name) in bindings {
+                    keymaps.bind(&layer, &keymap::parse_keys(keys).unwrap(), name);
+                }
+            }
+        }
         Ok(Handler {
             screen,
-            workspace: Workspace::new()?,
+            workspace: workspace,
             commands: commands,
-            shortcut: shortcut,
+            prefix: None,
+            describing: None,
+            hint_ticks: None,
+            hinting: false,
+            ticks: 0,
         })
     }
 
+    /// Open a file given from the command line.
+    pub fn open_file(
+        &mut self,
+        path: &str,
+        cursor: Option<hq::Pair>,
+        read_only: bool,
+    ) -> ResultBox<()> {
+        let buffer_name = self.workspace.open_file(path, cursor, read_only)?;
+        self.handle_event(ui::Request::OpenBuffer(buffer_name))?;
+        Ok(())
+    }
+
+    /// Open a string, e.g. the content of STDIN, as a buffer.
+    pub fn open_string(&mut self, name: &str, content: &str, read_only: bool) -> ResultBox<()> {
+        let buffer_name = self.workspace.open_string(name, content, read_only);
+        self.handle_event(ui::Request::OpenBuffer(buffer_name))?;
+        Ok(())
+    }
+
     /// Consume event from Io.
     pub fn request(&mut self, e: hq::Request) -> ResultBox<hq::Response> {
-        use hq::shortcut;
+        if let hq::Request::Keyboard(k) = e {
+            let first = !self.workspace.keymaps().pending();
+            self.workspace.macros().record(k, first);
+        }
+        let res = self.dispatch(e)?;
+        match self.workspace.macros().take_playback() {
+            Some((keys, times)) => {
+                let res = self.play_macro(&keys, times);
+                self.workspace.macros().finish_playback();
+                res
+            }
+            None => Ok(res),
+        }
+    }
+
+    /// Replay the keys until done `times` times or failed, and refresh the screen.
+    /// Quitting in the middle stops the replay and quits.
+    fn play_macro(&mut self, keys: &[term::Key], times: usize) -> ResultBox<hq::Response> {
+        let mut done = 0;
+        while done < times && !self.workspace.macros().failed() {
+            for &k in keys {
+                if let hq::Response::Quit = self.dispatch(hq::Request::Keyboard(k))? {
+                    return Ok(hq::Response::Quit);
+                }
+                if self.workspace.macros().failed() {
+                    break;
+                }
+            }
+            done += 1;
+        }
+        if self.workspace.macros().failed() {
+            self.handle_event(ui::Request::CommandBar(ui::CommandBar::Notify(
+                format!("Keyboard macro stopped at {} of {} times.", done, times),
+            )))
+        } else {
+            let name = self.workspace.cur_name();
+            self.handle_event(ui::Request::OpenBuffer(name))
+        }
+    }
+
+    /// Route the event through the replace session, the prefix argument,
+    /// the keymaps and the screen.
+    fn dispatch(&mut self, e: hq::Request) -> ResultBox<hq::Response> {
+        let key = if let hq::Request::Keyboard(k) = e {
+            Some(k)
+        } else {
+            None
+        };
+        if let Some(k) = key {
+            // The keymaps are chosen by the focus when a key sequence starts.
+            let layers = self.layers();
+            self.workspace.keymaps().set_active(layers);
+            if let Some(keys) = self.describing.take() {
+                return self.describe_key(keys, k);
+            }
+            // Quitting goes to the keymaps wherever the input is.
+            let quit = self.workspace.keymaps().quits(k);
+            // The replace session takes every other key.
+            if !quit {
+                if let Some(req) = self.workspace.on_replace_key(k)? {
+                    return self.handle_event(req);
+                }
+            }
+            if !quit && !self.workspace.keymaps().pending() {
+                if let Some(prefix) = Prefix::key(self.prefix, k) {
+                    self.prefix = Some(prefix);
+                    return self.handle_event(ui::Request::CommandBar(
+                        ui::CommandBar::Notify(prefix.to_string()),
+                    ));
+                }
+            }
+            // The next command or motion takes the count.
+            self.workspace.set_prefix(self.prefix.map(|p| p.count()));
+        }
         let e = if let hq::Request::Keyboard(k) = e {
-            match self.shortcut.key(k) {
-                shortcut::Response::More(s) => ui::Request::CommandBar(ui::CommandBar::Shortcut(s)),
-                shortcut::Response::Some(s) => self.call(&s).unwrap(),
-                _ => e.into_ui(),
+            let prompt = self.workspace.keymaps().is_active(&Layer::Prompt);
+            match self.workspace.keymaps().key(k) {
+                // The key sequence is not shown over the prompt.
+                keymap::Response::More(_) if prompt => None,
+                keymap::Response::More(s) => Some(self.more_keys(s)),
+                keymap::Response::Some(ref s) if s == keymap::QUIT => Some(self.quit()),
+                keymap::Response::Some(s) => self.call(&s),
+                keymap::Response::Cancel => Some(self.quit()),
+                // Characters insert themselves.
+                keymap::Response::None if Handler::self_insert(k) => Some(e.into_ui()),
+                // The prompt ignores the other keys, keeping the input.
+                _ if prompt => None,
+                _ => {
+                    self.workspace.macros().fail();
+                    let keys = self.workspace.keymaps().last_keys();
+                    Some(ui::Request::CommandBar(ui::CommandBar::Notify(
+                        format!("{} is undefined", keys_to_string(&keys)),
+                    )))
+                }
             }
         } else {
-            e.into_ui()
+            Some(e.into_ui())
+        };
+        let res = match e {
+            Some(e) => self.handle_event(e),
+            None => Ok(hq::Response::None),
+        };
+        if key.is_some() && !self.workspace.keymaps().pending() {
+            self.prefix = None;
+            self.workspace.set_prefix(None);
+            self.hint_ticks = None;
+            self.hinting = false;
+        }
+        res
+    }
+
+    /// Forget every pending input: the command waiting for its arguments,
+    /// the key sequence, the prefix argument and `describe-key`.
+    fn cancel(&mut self) {
+        self.commands.cancel();
+        self.workspace.keymaps().cancel();
+        self.prefix = None;
+        self.workspace.set_prefix(None);
+        self.describing = None;
+        self.hint_ticks = None;
+        self.hinting = false;
+    }
+
+    /// Forget every pending input and run `keyboard-quit`.
+    fn quit(&mut self) -> ui::Request {
+        self.cancel();
+        self.call(keymap::QUIT).unwrap()
+    }
+
+    /// True iff the key inserts itself unless bound.
+    fn self_insert(k: term::Key) -> bool {
+        if let term::Key::Char(_) = k { true } else { false }
+    }
+
+    /// The keymaps searched for the next key sequence, the most specific first.
+    fn layers(&self) -> Vec<Layer> {
+        let mut res = vec![];
+        if self.screen.command_bar_focused() {
+            res.push(Layer::Prompt);
+        }
+        if let Some(mode) = self.workspace.cur_mode() {
+            res.push(Layer::Mode(String::from(mode)));
+        }
+        res.push(Layer::Window);
+        res.push(Layer::Global);
+        res
+    }
+
+    /// Show the key sequence being typed, with the hints once they are shown.
+    fn more_keys(&mut self, s: String) -> ui::Request {
+        if self.hinting {
+            self.hint()
+        } else {
+            self.hint_ticks = Some(0);
+            ui::Request::CommandBar(ui::CommandBar::Shortcut(s))
+        }
+    }
+
+    /// The keys continuing the key sequence, with the bound commands.
+    fn hint(&mut self) -> ui::Request {
+        let keymaps = self.workspace.keymaps();
+        let hints = keymaps
+            .continuations()
+            .into_iter()
+            .map(|(k, name)| (k.to_string(), name))
+            .collect();
+        ui::Request::CommandBar(ui::CommandBar::Hint(
+            format!("{}-", keys_to_string(keymaps.current())),
+            hints,
+        ))
+    }
+
+    /// Describe the command bound to the keys, or wait for more keys.
+    fn describe_key(
+        &mut self,
+        mut keys: Vec<term::Key>,
+        k: term::Key,
+    ) -> ResultBox<hq::Response> {
+        keys.push(k);
+        let req = match self.workspace.keymaps().key(k) {
+            keymap::Response::More(s) => {
+                self.describing = Some(keys);
+                self.more_keys(s)
+            }
+            keymap::Response::Some(name) => {
+                let text = format!(
+                    "{} runs the command {}.\n\n{}",
+                    keys_to_string(&keys),
+                    name,
+                    self.describe_command(&name)
+                );
+                self.show_help(&text)
+            }
+            keymap::Response::Cancel => self.quit(),
+            keymap::Response::None if Handler::self_insert(k) => {
+                ui::Request::CommandBar(ui::CommandBar::Notify(
+                    format!("{} inserts itself", keys_to_string(&keys)),
+                ))
+            }
+            _ => {
+                ui::Request::CommandBar(ui::CommandBar::Notify(
+                    format!("{} is undefined", keys_to_string(&keys)),
+                ))
+            }
+        };
+        if !self.workspace.keymaps().pending() {
+            self.hint_ticks = None;
+            self.hinting = false;
+        }
+        self.handle_event(req)
+    }
+
+    fn describe_command(&mut self, name: &str) -> String {
+        let keys = self.workspace.keymaps().keys_for(name);
+        match self.commands.get(name) {
+            Some(cmd) => help::describe_command(cmd, &keys),
+            None => format!("{} is not a command.\n", name),
+        }
+    }
+
+    /// Show the text in the read-only help buffer.
+    fn show_help(&mut self, text: &str) -> ui::Request {
+        let name = self.workspace.open_string(help::HELP_BUFFER, text, true);
+        ui::Request::OpenBuffer(name)
+    }
+
+    /// Build the help requested by a command.
+    fn help(&mut self, help: Help) -> Option<ui::Request> {
+        let text = match help {
+            Help::Key => {
+                self.describing = Some(vec![]);
+                return None;
+            }
+            Help::Command(name) => self.describe_command(&name),
+            Help::Keybindings => {
+                let layers: Vec<String> = self.workspace
+                    .keymaps()
+                    .bindings()
+                    .iter()
+                    .map(|&(ref layer, ref bindings)| {
+                        format!(
+                            "{} keys:\n\n{}",
+                            layer.to_string(),
+                            help::list_keybindings(bindings)
+                        )
+                    })
+                    .collect();
+                layers.join("\n")
+            }
         };
-        self.handle_event(e)
+        Some(self.show_help(&text))
+    }
+
+    /// Consume the timer event from Io.
+    pub fn tick(&mut self) -> ResultBox<hq::Response> {
+        self.ticks += 1;
+        if self.ticks % AUTO_SAVE_TICKS == 0 {
+            self.workspace.auto_save();
+        }
+        if let Some(n) = self.hint_ticks {
+            if (n + 1) * TICK_MS >= self.workspace.which_key_delay() {
+                self.hint_ticks = None;
+                self.hinting = true;
+                let req = self.hint();
+                return self.handle_event(req);
+            }
+            self.hint_ticks = Some(n + 1);
+        }
+        // The report would replace the input being typed, so wait for the command bar to close.
+        if self.ticks % CHECK_FILES_TICKS != 0 || self.commands.pending() ||
+            self.screen.command_bar_focused()
+        {
+            return Ok(hq::Response::None);
+        }
+        let check = self.workspace.check_files();
+        let mut msgs = vec![];
+        if !check.reverted.is_empty() {
+            msgs.push(format!("Reverted {}.", check.reverted.join(", ")));
+        }
+        match check.conflict {
+            Some(name) => {
+                // Wait for the confirmation of `revert-buffer`.
+                self.commands.query("revert-buffer");
+                msgs.push(format!("{} changed on disk; discard your changes? (yes or no) ", name));
+                let req = self.prompt(&Arg::yes_no(&msgs.join(" ")), None);
+                self.handle_event(req)
+            }
+            None if !msgs.is_empty() => {
+                self.handle_event(ui::Request::CommandBar(ui::CommandBar::Notify(msgs.join(" "))))
+            }
+            None => Ok(hq::Response::None),
+        }
+    }
+
+    /// Keep unsaved works, e.g. before the editor dies.
+    pub fn auto_save(&mut self) {
+        self.workspace.auto_save();
     }
 
     fn handle_event(&mut self, e: ui::Request) -> ResultBox<hq::Response> {
@@ -65,20 +812,68 @@ impl Handler {
                 let req = self.call(&s).unwrap();
                 self.handle_event(req)
             }
+            ui::Response::Notify(m) => {
+                self.handle_event(ui::Request::CommandBar(ui::CommandBar::Notify(m)))
+            }
             e => panic!("{:?}", e),
         }
     }
 
-    /// Run a given command.
+    /// The candidates of the input completed by Tab.
+    fn candidates(&self, arg: &Arg) -> Vec<String> {
+        match arg.kind {
+            ArgKind::Buffer => self.workspace.buffer_names(),
+            ArgKind::Command => self.commands.names(),
+            _ => arg.choices(),
+        }
+    }
+
+    /// Ask the argument in the command bar, after the error if any.
+    fn prompt(&self, arg: &Arg, error: Option<String>) -> ui::Request {
+        if arg.kind == ArgKind::Path {
+            return ui::Request::CommandBar(ui::CommandBar::Navigate(String::from(".")));
+        }
+        let message = match error {
+            Some(e) => format!("[{}] {}", e, arg.message()),
+            None => arg.message(),
+        };
+        ui::Request::CommandBar(ui::CommandBar::Prompt(ui::Prompt {
+            message: message,
+            history: arg.history,
+            candidates: self.candidates(arg),
+        }))
+    }
+
+    /// Run a given command, or take its argument after checking it.
     pub fn call(&mut self, command: &str) -> Option<ui::Request> {
-        match self.commands.query(command) {
-            commands::Response::Func(func, args) => func(&mut self.workspace, args).ok(),
-            commands::Response::Require(Arg::Path(_)) => {
-                Some(ui::Request::CommandBar(
-                    ui::CommandBar::Navigate(String::from(".")),
-                ))
+        let mut input = String::from(command);
+        if let Some(arg) = self.commands.next_arg() {
+            if input.is_empty() {
+                if let Some(ref value) = arg.default {
+                    input = value.clone();
+                }
+            }
+            if let Err(e) = arg.validate(&input, &self.candidates(&arg)) {
+                // Ask again, keeping the command waiting.
+                self.workspace.macros().fail();
+                return Some(self.prompt(&arg, Some(e)));
+            }
+        }
+        match self.commands.query(&input) {
+            commands::Response::Func(func, args) => {
+                let res = func(&mut self.workspace, args).unwrap_or_else(|e| {
+                    self.workspace.macros().fail();
+                    ui::Request::CommandBar(ui::CommandBar::Notify(format!("{}", e)))
+                });
+                match self.workspace.take_help() {
+                    Some(help) => self.help(help).or(Some(res)),
+                    None => Some(res),
+                }
             }
+            commands::Response::Operation(op) => Some(ui::Request::Operation(op)),
+            commands::Response::Require(arg) => Some(self.prompt(&arg, None)),
             commands::Response::Message(m) => {
+                self.workspace.macros().fail();
                 Some(ui::Request::CommandBar(ui::CommandBar::Notify(m)))
             }
         }
@@ -0,0 +1,80 @@
+use term;
+use hq::commands::Command;
+use hq::keymap::keys_to_string;
+
+/// The name of the read-only buffer showing the help.
+pub const HELP_BUFFER: &'static str = "<help>";
+
+/// A help requested by a command, built by the handler owning the commands and the keys.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Help {
+    /// Describe the command run by the key sequence typed next.
+    Key,
+    Command(String),
+    Keybindings,
+}
+
+/// Describe the command with its arguments and the keys bound to it.
+pub fn describe_command(cmd: &Command, keys: &[Vec<term::Key>]) -> String {
+    let mut res = format!("{}\n\n{}\n", cmd.name(), cmd.doc());
+    if cmd.args_len() > 0 {
+        res.push_str("\nArguments:\n");
+        for i in 0..cmd.args_len() {
+            res.push_str(&format!("  {}\n", cmd.arg(i).prompt.trim()));
+        }
+    }
+    if keys.is_empty() {
+        res.push_str("\nNot bound to any key.\n");
+    } else {
+        let keys: Vec<String> = keys.iter().map(|keys| keys_to_string(keys)).collect();
+        res.push_str(&format!("\nBound to {}.\n", keys.join(", ")));
+    }
+    res
+}
+
+/// List the key sequences with the commands, aligned in two columns.
+pub fn list_keybindings(bindings: &[(Vec<term::Key>, String)]) -> String {
+    let keys: Vec<String> = bindings.iter().map(|&(ref keys, _)| keys_to_string(keys)).collect();
+    let width = keys.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut res = String::new();
+    for (keys, &(_, ref name)) in keys.iter().zip(bindings) {
+        res.push_str(&format!("{:2$}  {}\n", keys, name, width));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ui;
+    use hq::{Arg, Workspace};
+    use hq::commands::Action;
+    use util::ResultBox;
+
+    fn nop(_: &mut Workspace, _: Vec<String>) -> ResultBox<ui::Request> {
+        Ok(ui::Request::Quit)
+    }
+
+    #[test]
+    fn test_describe() {
+        let cmd = Command::new(
+            "goto-line",
+            "Move the cursor to LINE.",
+            vec![Arg::text("Goto line: ")],
+            Action::Func(nop),
+        );
+        let keys = vec![vec![term::Key::Meta('g'), term::Key::Char('g')]];
+        assert_eq!(
+            describe_command(&cmd, &keys),
+            "goto-line\n\nMove the cursor to LINE.\n\nArguments:\n  Goto line:\n\nBound to M-g g.\n"
+        );
+        let bindings = vec![
+            (vec![term::Key::Ctrl('s')], String::from("search-forward")),
+            (vec![term::Key::Ctrl('x'), term::Key::Ctrl('s')], String::from("save-buffer")),
+        ];
+        assert_eq!(
+            list_keybindings(&bindings),
+            "C-s      search-forward\nC-x C-s  save-buffer\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use util::{self, ResultBox};
+
+/// Entries kept for each kind of the history.
+const HISTORY_MAX: usize = 100;
+
+/// The kind of the input, each of which has its own history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum History {
+    Command,
+    Path,
+    Search,
+    Replace,
+    Text,
+}
+
+impl History {
+    fn name(&self) -> &'static str {
+        match *self {
+            History::Command => "command",
+            History::Path => "path",
+            History::Search => "search",
+            History::Replace => "replace",
+            History::Text => "text",
+        }
+    }
+}
+
+/// The histories of the command bar inputs, saved in the state directory.
+#[derive(Default)]
+pub struct Histories {
+    lists: BTreeMap<String, Vec<String>>,
+    path: Option<PathBuf>,
+}
+
+impl Histories {
+    /// Load the histories saved in the previous sessions.
+    pub fn load() -> Histories {
+        let path = util::state_dir().map(|dir| dir.join("history"));
+        let mut s = String::new();
+        let read = path.as_ref()
+            .and_then(|path| fs::File::open(path).ok())
+            .and_then(|mut f| f.read_to_string(&mut s).ok());
+        Histories {
+            lists: if read.is_some() {
+                Histories::parse(&s)
+            } else {
+                BTreeMap::new()
+            },
+            path: path,
+        }
+    }
+
+    /// Parse the lines of a kind followed by an entry.
+    fn parse(s: &str) -> BTreeMap<String, Vec<String>> {
+        let mut res = BTreeMap::new();
+        for line in s.lines() {
+            let mut it = line.splitn(2, ' ');
+            if let (Some(kind), Some(entry)) = (it.next(), it.next()) {
+                res.entry(String::from(kind))
+                    .or_insert_with(Vec::new)
+                    .push(String::from(entry));
+            }
+        }
+        res
+    }
+
+    /// Format the histories as `parse` reads.
+    fn format(&self) -> String {
+        let mut res = String::new();
+        for (kind, list) in &self.lists {
+            for entry in list {
+                res.push_str(&format!("{} {}\n", kind, entry));
+            }
+        }
+        res
+    }
+
+    /// The entries from the oldest.
+    pub fn get(&self, kind: History) -> &[String] {
+        match self.lists.get(kind.name()) {
+            Some(list) => list,
+            None => &[],
+        }
+    }
+
+    /// Add the newest entry, moving the same one if any, and save the histories.
+    pub fn add(&mut self, kind: History, entry: &str) -> ResultBox<()> {
+        if entry.is_empty() {
+            return Ok(());
+        }
+        {
+            let list = self.lists.entry(String::from(kind.name())).or_insert_with(
+                Vec::new,
+            );
+            list.retain(|s| s != entry);
+            list.push(String::from(entry));
+            if list.len() > HISTORY_MAX {
+                let n = list.len() - HISTORY_MAX;
+                list.drain(..n);
+            }
+        }
+        if let Some(ref path) = self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::File::create(path)?.write_all(self.format().as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut histories: Histories = Default::default();
+        histories.add(History::Search, "foo").unwrap();
+        histories.add(History::Search, "bar baz").unwrap();
+        histories.add(History::Search, "foo").unwrap();
+        histories.add(History::Search, "").unwrap();
+        histories.add(History::Path, "src").unwrap();
+        assert_eq!(histories.get(History::Search), &["bar baz", "foo"]);
+        assert!(histories.get(History::Command).is_empty());
+        let formatted = histories.format();
+        assert_eq!(formatted, "path src\nsearch bar baz\nsearch foo\n");
+        assert_eq!(Histories::parse(&formatted), histories.lists);
+    }
+}
@@ -0,0 +1,471 @@
+use term;
+use std::mem;
+use std::ops::Deref;
+use std::collections::BTreeMap;
+
+/// The command quitting, which also cancels a key sequence wherever it is bound.
+pub const QUIT: &'static str = "keyboard-quit";
+
+/// Render the key sequence, e.g. `C-x C-f`.
+pub fn keys_to_string(keys: &[term::Key]) -> String {
+    let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+    keys.join(" ")
+}
+
+/// Parse the key sequence written by `keys_to_string`.
+pub fn parse_keys(s: &str) -> Option<Vec<term::Key>> {
+    let keys: Option<Vec<term::Key>> = s.split_whitespace().map(term::Key::parse).collect();
+    keys.and_then(|keys| if keys.is_empty() { None } else { Some(keys) })
+}
+
+/// A keymap in the stack, searched from the most specific one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    /// Active while the command bar takes the keys.
+    Prompt,
+    /// Active in the buffers of the major mode.
+    Mode(String),
+    Window,
+    Global,
+}
+
+impl ToString for Layer {
+    fn to_string(&self) -> String {
+        match *self {
+            Layer::Prompt => String::from("prompt"),
+            Layer::Mode(ref name) => format!("{} mode", name),
+            Layer::Window => String::from("window"),
+            Layer::Global => String::from("global"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Internal { children: BTreeMap<term::Key, Box<Node>>, },
+    Leaf(String),
+}
+
+#[derive(Debug)]
+pub enum Response {
+    Some(String),
+    More(String),
+    Undefined,
+    /// `keyboard-quit` in the middle of a key sequence.
+    Cancel,
+    None,
+}
+
+impl Node {
+    /// Construct a new internal node.
+    fn new_inner() -> Node {
+        Node::Internal { children: BTreeMap::new() }
+    }
+
+    /// Construct a new leaf node.
+    fn new_leaf(value: &str) -> Node {
+        Node::Leaf(String::from(value))
+    }
+
+    /// Find the next node.
+    fn get(&self, key: term::Key) -> Option<&Node> {
+        match *self {
+            Node::Internal { ref children } => children.get(&key).map(|n| n.deref()),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            Node::Internal { ref children } => children.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Bind the keys, replacing a binding of the keys or of a prefix of them.
+    /// Return the command bound before, if any.
+    fn bind(&mut self, keys: &[term::Key], value: &str) -> Option<String> {
+        if let Node::Leaf(_) = *self {
+            *self = Node::new_inner();
+        }
+        if let Node::Internal { ref mut children } = *self {
+            if keys.len() == 1 {
+                return match children.insert(keys[0], Box::new(Node::new_leaf(value))) {
+                    Some(node) => {
+                        if let Node::Leaf(s) = *node { Some(s) } else { None }
+                    }
+                    None => None,
+                };
+            }
+            return children
+                .entry(keys[0])
+                .or_insert_with(|| Box::new(Node::new_inner()))
+                .bind(&keys[1..], value);
+        }
+        None
+    }
+
+    /// Remove the binding of the keys, or every binding under them.
+    fn unbind(&mut self, keys: &[term::Key]) -> bool {
+        if let Node::Internal { ref mut children } = *self {
+            if keys.len() == 1 {
+                return children.remove(&keys[0]).is_some();
+            }
+            let (removed, empty) = match children.get_mut(&keys[0]) {
+                Some(child) => (child.unbind(&keys[1..]), child.is_empty()),
+                None => return false,
+            };
+            if empty {
+                children.remove(&keys[0]);
+            }
+            return removed;
+        }
+        false
+    }
+
+    /// Collect the key sequences under the node with the command names.
+    fn bindings(&self, prefix: &mut Vec<term::Key>, res: &mut Vec<(Vec<term::Key>, String)>) {
+        match *self {
+            Node::Internal { ref children } => {
+                for (key, child) in children {
+                    prefix.push(*key);
+                    child.bindings(prefix, res);
+                    prefix.pop();
+                }
+            }
+            Node::Leaf(ref s) => res.push((prefix.clone(), s.clone())),
+        }
+    }
+}
+
+/// The key sequences bound to the command names.
+#[derive(Debug)]
+pub struct Keymap {
+    head: Node,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap { head: Node::new_inner() }
+    }
+}
+
+impl Keymap {
+    fn node(&self, keys: &[term::Key]) -> Option<&Node> {
+        keys.iter().fold(Some(&self.head), |acc, key| acc.and_then(|n| n.get(*key)))
+    }
+
+    /// Bind the keys to the command, overriding the binding if any.
+    /// Return the command bound before, if any.
+    pub fn bind(&mut self, keys: &[term::Key], name: &str) -> Option<String> {
+        if keys.is_empty() {
+            return None;
+        }
+        self.head.bind(keys, name)
+    }
+
+    /// Return false iff nothing is bound to the keys.
+    pub fn unbind(&mut self, keys: &[term::Key]) -> bool {
+        !keys.is_empty() && self.head.unbind(keys)
+    }
+
+    /// Every key sequence with the bound command name, in the order of the keys.
+    pub fn bindings(&self) -> Vec<(Vec<term::Key>, String)> {
+        let mut res = vec![];
+        self.head.bindings(&mut vec![], &mut res);
+        res
+    }
+}
+
+/// The stack of the keymaps, resolving the key sequence being typed.
+#[derive(Debug, Default)]
+pub struct Keymaps {
+    global: Keymap,
+    window: Keymap,
+    prompt: Keymap,
+    modes: BTreeMap<String, Keymap>,
+    /// The layers searched for the key sequence being typed.
+    active: Vec<Layer>,
+    current: Vec<term::Key>,
+    /// The key sequence ended last.
+    last: Vec<term::Key>,
+}
+
+impl Keymaps {
+    pub fn new() -> Keymaps {
+        Keymaps {
+            active: vec![Layer::Global],
+            ..Default::default()
+        }
+    }
+
+    fn keymap(&self, layer: &Layer) -> Option<&Keymap> {
+        match *layer {
+            Layer::Prompt => Some(&self.prompt),
+            Layer::Mode(ref name) => self.modes.get(name),
+            Layer::Window => Some(&self.window),
+            Layer::Global => Some(&self.global),
+        }
+    }
+
+    fn keymap_mut(&mut self, layer: &Layer) -> &mut Keymap {
+        match *layer {
+            Layer::Prompt => &mut self.prompt,
+            Layer::Mode(ref name) => {
+                self.modes.entry(name.clone()).or_insert_with(Default::default)
+            }
+            Layer::Window => &mut self.window,
+            Layer::Global => &mut self.global,
+        }
+    }
+
+    /// Bind the keys in the layer, overriding the binding if any.
+    /// Return the command bound before, if any.
+    pub fn bind(&mut self, layer: &Layer, keys: &[term::Key], name: &str) -> Option<String> {
+        self.keymap_mut(layer).bind(keys, name)
+    }
+
+    /// Return false iff nothing is bound to the keys in the layer.
+    pub fn unbind(&mut self, layer: &Layer, keys: &[term::Key]) -> bool {
+        self.keymap_mut(layer).unbind(keys)
+    }
+
+    /// Set the layers searched from the next key sequence, the most specific first.
+    pub fn set_active(&mut self, layers: Vec<Layer>) {
+        if !self.pending() {
+            self.active = layers;
+        }
+    }
+
+    /// True iff the layer is searched for the key sequence.
+    #[inline]
+    pub fn is_active(&self, layer: &Layer) -> bool {
+        self.active.contains(layer)
+    }
+
+    /// The bindings of every non-empty layer, the most specific first.
+    pub fn bindings(&self) -> Vec<(Layer, Vec<(Vec<term::Key>, String)>)> {
+        let mut layers = vec![Layer::Prompt];
+        layers.extend(self.modes.keys().map(|name| Layer::Mode(name.clone())));
+        layers.push(Layer::Window);
+        layers.push(Layer::Global);
+        layers
+            .into_iter()
+            .filter_map(|layer| {
+                let bindings = self.keymap(&layer).map(|m| m.bindings()).unwrap_or_default();
+                if bindings.is_empty() { None } else { Some((layer, bindings)) }
+            })
+            .collect()
+    }
+
+    /// The key sequences bound to the command in any layer.
+    pub fn keys_for(&self, name: &str) -> Vec<Vec<term::Key>> {
+        self.bindings()
+            .into_iter()
+            .flat_map(|(_, bindings)| bindings)
+            .filter(|&(_, ref s)| s == name)
+            .map(|(keys, _)| keys)
+            .collect()
+    }
+
+    /// The next keys of the sequence being typed, with the bound command names.
+    /// A key continuing the sequence further is shown as `+prefix`.
+    pub fn continuations(&self) -> Vec<(term::Key, String)> {
+        let mut res = BTreeMap::new();
+        for layer in &self.active {
+            let node = self.keymap(layer).and_then(|m| m.node(&self.current));
+            if let Some(&Node::Internal { ref children }) = node {
+                for (key, child) in children {
+                    res.entry(*key).or_insert_with(|| match **child {
+                        Node::Leaf(ref s) => s.clone(),
+                        _ => String::from("+prefix"),
+                    });
+                }
+            }
+        }
+        res.into_iter().collect()
+    }
+
+    /// The key sequence being typed.
+    #[inline]
+    pub fn current(&self) -> &[term::Key] {
+        &self.current
+    }
+
+    /// True iff a key sequence is being typed.
+    #[inline]
+    pub fn pending(&self) -> bool {
+        !self.current.is_empty()
+    }
+
+    /// The key sequence ended last, e.g. to be reported as undefined.
+    #[inline]
+    pub fn last_keys(&self) -> Vec<term::Key> {
+        self.last.clone()
+    }
+
+    /// End the key sequence being typed.
+    #[inline]
+    pub fn cancel(&mut self) {
+        self.last = mem::replace(&mut self.current, vec![]);
+    }
+
+    /// The node of the keys in the first active layer having them.
+    fn find(&self, keys: &[term::Key]) -> Option<&Node> {
+        self.active
+            .iter()
+            .filter_map(|layer| self.keymap(layer).and_then(|m| m.node(keys)))
+            .next()
+    }
+
+    /// True iff the key alone runs `QUIT` in the active layers.
+    pub fn quits(&self, key: term::Key) -> bool {
+        match self.find(&[key]) {
+            Some(&Node::Leaf(ref s)) => s == QUIT,
+            _ => false,
+        }
+    }
+
+    /// Handle key event. The first layer having the key sequence decides
+    /// whether it runs a command or waits for more keys.
+    pub fn key(&mut self, key: term::Key) -> Response {
+        if self.pending() && self.quits(key) {
+            self.cancel();
+            return Response::Cancel;
+        }
+        self.current.push(key);
+        let found = self.find(&self.current).map(|node| match *node {
+            Node::Leaf(ref s) => Some(s.clone()),
+            _ => None,
+        });
+        match found {
+            Some(Some(s)) => {
+                self.cancel();
+                Response::Some(s)
+            }
+            Some(None) => Response::More(format!("{}-", keys_to_string(&self.current))),
+            None => {
+                let res = if self.current.len() > 1 {
+                    Response::Undefined
+                } else {
+                    Response::None
+                };
+                self.cancel();
+                res
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(s: &str) -> Vec<term::Key> {
+        parse_keys(s).unwrap()
+    }
+
+    #[test]
+    fn test_key() {
+        let mut keymaps = Keymaps::new();
+        keymaps.bind(&Layer::Global, &keys("C-x C-c"), "exit");
+        keymaps.bind(&Layer::Global, &keys("C-g"), QUIT);
+        if let Response::None = keymaps.key(term::Key::Ctrl('c')) {
+        } else {
+            panic!("failed")
+        };
+        match keymaps.key(term::Key::Ctrl('x')) {
+            Response::More(s) => assert_eq!(s, "C-x-"),
+            _ => panic!("failed"),
+        }
+        if let Response::Undefined = keymaps.key(term::Key::Ctrl('x')) {
+        } else {
+            panic!("failed")
+        };
+        keymaps.key(term::Key::Ctrl('x'));
+        if let Response::Cancel = keymaps.key(term::Key::Ctrl('g')) {
+        } else {
+            panic!("failed")
+        };
+        assert!(!keymaps.pending());
+        assert_eq!(keymaps.last_keys(), keys("C-x"));
+        // The key quitting is rebound.
+        keymaps.unbind(&Layer::Global, &keys("C-g"));
+        keymaps.bind(&Layer::Global, &keys("C-]"), QUIT);
+        keymaps.key(term::Key::Ctrl('x'));
+        if let Response::Undefined = keymaps.key(term::Key::Ctrl('g')) {
+        } else {
+            panic!("failed")
+        };
+        keymaps.key(term::Key::Ctrl('x'));
+        if let Response::Cancel = keymaps.key(term::Key::Ctrl(']')) {
+        } else {
+            panic!("failed")
+        };
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut keymap: Keymap = Default::default();
+        assert_eq!(keymap.bind(&keys("C-x u"), "undo"), None);
+        assert_eq!(keymap.bind(&keys("C-x u"), "redo"), Some(String::from("undo")));
+        assert_eq!(keymap.bind(&keys("C-x u"), "undo"), Some(String::from("redo")));
+        keymap.bind(&keys("C-x C-c"), "exit");
+        keymap.bind(&keys("C-_"), "undo");
+        let bindings: Vec<(String, String)> = keymap.bindings()
+            .iter()
+            .map(|&(ref keys, ref s)| (keys_to_string(keys), s.clone()))
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                (String::from("C-_"), String::from("undo")),
+                (String::from("C-x u"), String::from("undo")),
+                (String::from("C-x C-c"), String::from("exit")),
+            ]
+        );
+        // A binding replaces the prefix.
+        keymap.bind(&keys("C-_ a"), "foo");
+        assert!(keymap.unbind(&keys("C-x u")));
+        assert!(!keymap.unbind(&keys("C-x u")));
+        assert!(keymap.unbind(&keys("C-x C-c")));
+        assert_eq!(keymap.bindings(), vec![(keys("C-_ a"), String::from("foo"))]);
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut keymaps = Keymaps::new();
+        keymaps.bind(&Layer::Global, &keys("C-x C-f"), "find-file");
+        keymaps.bind(&Layer::Global, &keys("C-x u"), "undo");
+        keymaps.bind(&Layer::Global, &keys("TAB"), "indent");
+        keymaps.bind(&Layer::Prompt, &keys("TAB"), "complete");
+        keymaps.bind(&Layer::Prompt, &keys("C-x 4"), "other");
+        keymaps.set_active(vec![Layer::Prompt, Layer::Global]);
+        match keymaps.key(term::Key::Tab) {
+            Response::Some(s) => assert_eq!(s, "complete"),
+            _ => panic!("failed"),
+        }
+        keymaps.key(term::Key::Ctrl('x'));
+        assert_eq!(
+            keymaps.continuations(),
+            vec![
+                (term::Key::Char('4'), String::from("other")),
+                (term::Key::Char('u'), String::from("undo")),
+                (term::Key::Ctrl('f'), String::from("find-file")),
+            ]
+        );
+        // The layers are kept until the key sequence ends.
+        keymaps.set_active(vec![Layer::Global]);
+        match keymaps.key(term::Key::Char('4')) {
+            Response::Some(s) => assert_eq!(s, "other"),
+            _ => panic!("failed"),
+        }
+        keymaps.set_active(vec![Layer::Global]);
+        match keymaps.key(term::Key::Tab) {
+            Response::Some(s) => assert_eq!(s, "indent"),
+            _ => panic!("failed"),
+        }
+        assert_eq!(keymaps.keys_for("undo"), vec![keys("C-x u")]);
+        assert_eq!(keymaps.bindings().len(), 2);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use term;
+use util::{self, ResultBox};
+
+/// Keyboard macros: the one being recorded, the last one and the named ones.
+#[derive(Default)]
+pub struct Macros {
+    recording: Option<Vec<term::Key>>,
+    /// Where the key sequence being typed starts in the recording.
+    sequence_start: usize,
+    last: Vec<term::Key>,
+    named: BTreeMap<String, Vec<term::Key>>,
+    /// The keys to be replayed with the count.
+    playback: Option<(Vec<term::Key>, usize)>,
+    playing: bool,
+    failed: bool,
+}
+
+impl Macros {
+    /// Load the named macros saved in the configuration directory.
+    pub fn load() -> Macros {
+        let mut res: Macros = Default::default();
+        let mut s = String::new();
+        let read = Macros::path()
+            .and_then(|path| fs::File::open(path).ok())
+            .and_then(|mut f| f.read_to_string(&mut s).ok());
+        if read.is_some() {
+            res.named = Macros::parse(&s);
+        }
+        res
+    }
+
+    /// The file of the named macros.
+    fn path() -> Option<PathBuf> {
+        util::config_dir().map(|dir| dir.join("macros"))
+    }
+
+    /// Parse the lines of a name followed by the keys.
+    fn parse(s: &str) -> BTreeMap<String, Vec<term::Key>> {
+        let mut res = BTreeMap::new();
+        for line in s.lines() {
+            let mut words = line.split_whitespace();
+            let name = match words.next() {
+                Some(name) => name,
+                None => continue,
+            };
+            let keys: Option<Vec<term::Key>> = words.map(term::Key::parse).collect();
+            if let Some(keys) = keys {
+                res.insert(String::from(name), keys);
+            }
+        }
+        res
+    }
+
+    /// Format the named macros as `parse` reads.
+    fn format(&self) -> String {
+        let mut res = String::new();
+        for (name, keys) in &self.named {
+            let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+            res.push_str(&format!("{} {}\n", name, keys.join(" ")));
+        }
+        res
+    }
+
+    #[inline]
+    pub fn recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Start recording. Return false iff already recording.
+    pub fn start(&mut self) -> bool {
+        if self.recording() {
+            return false;
+        }
+        self.recording = Some(vec![]);
+        self.sequence_start = 0;
+        true
+    }
+
+    /// Record a key unless replaying. `first` is true iff the key starts a key sequence.
+    pub fn record(&mut self, key: term::Key, first: bool) {
+        if self.playing {
+            return;
+        }
+        if let Some(ref mut keys) = self.recording {
+            if first {
+                self.sequence_start = keys.len();
+            }
+            keys.push(key);
+        }
+    }
+
+    /// Finish recording without the key sequence ending it.
+    /// Return the number of the keys, or None if not recording.
+    pub fn end(&mut self) -> Option<usize> {
+        let mut keys = self.recording.take()?;
+        keys.truncate(self.sequence_start);
+        let len = keys.len();
+        if len > 0 {
+            self.last = keys;
+        }
+        Some(len)
+    }
+
+    /// Drop the macro being recorded, keeping the last one.
+    #[inline]
+    pub fn abort(&mut self) {
+        self.recording = None;
+    }
+
+    /// Give a name to the last macro and save the named ones.
+    pub fn name_last(&mut self, name: &str) -> ResultBox<bool> {
+        if self.last.is_empty() {
+            return Ok(false);
+        }
+        self.named.insert(String::from(name), self.last.clone());
+        if let Some(path) = Macros::path() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::File::create(path)?.write_all(self.format().as_bytes())?;
+        }
+        Ok(true)
+    }
+
+    /// Request to replay the macro `times` times; the last one if `name` is None.
+    /// Return false iff there is no such macro, or a macro is being replayed.
+    pub fn play(&mut self, name: Option<&str>, times: usize) -> bool {
+        let keys = match name {
+            Some(name) => self.named.get(name).cloned().unwrap_or_else(Vec::new),
+            None => self.last.clone(),
+        };
+        if keys.is_empty() || self.playing {
+            return false;
+        }
+        self.playback = Some((keys, times));
+        true
+    }
+
+    /// Take the requested playback, and mark it started.
+    pub fn take_playback(&mut self) -> Option<(Vec<term::Key>, usize)> {
+        let res = self.playback.take();
+        self.playing = res.is_some();
+        self.failed = false;
+        res
+    }
+
+    #[inline]
+    pub fn finish_playback(&mut self) {
+        self.playing = false;
+    }
+
+    /// Stop the playback, e.g. by an error.
+    #[inline]
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    #[inline]
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut macros: Macros = Default::default();
+        assert_eq!(macros.end(), None);
+        assert!(macros.start());
+        assert!(!macros.start());
+        macros.record(term::Key::Char('a'), true);
+        macros.record(term::Key::Ctrl('n'), true);
+        macros.record(term::Key::Ctrl('x'), true);
+        macros.record(term::Key::Char(')'), false);
+        assert_eq!(macros.end(), Some(2));
+        assert!(macros.play(None, 3));
+        assert!(!macros.play(Some("none"), 1));
+        assert_eq!(
+            macros.take_playback(),
+            Some((vec![term::Key::Char('a'), term::Key::Ctrl('n')], 3))
+        );
+        macros.record(term::Key::Char('b'), true);
+        assert!(!macros.play(None, 1));
+        macros.finish_playback();
+        assert!(macros.play(None, 1));
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut macros: Macros = Default::default();
+        macros.start();
+        macros.record(term::Key::Char('a'), true);
+        macros.record(term::Key::Ctrl('x'), true);
+        macros.end();
+        macros.start();
+        macros.record(term::Key::Char('b'), true);
+        macros.abort();
+        assert!(!macros.recording());
+        assert_eq!(macros.end(), None);
+        assert!(macros.play(None, 1));
+        assert_eq!(macros.take_playback(), Some((vec![term::Key::Char('a')], 1)));
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let mut macros: Macros = Default::default();
+        macros.named = Macros::parse("next C-n C-a SPC\n\nbad C-\n");
+        assert_eq!(macros.format(), "next C-n C-a SPC\n");
+    }
+}
@@ -1,10 +1,17 @@
 pub use hq::enums::{Request, Response, Pair, Arg, Func};
 pub use hq::workspace::Workspace;
 pub use hq::handler::Handler;
+pub use hq::history::{History, Histories};
 
-mod shortcut;
+mod keymap;
 mod workspace;
 mod commands;
 mod enums;
 mod handler;
 mod fs;
+mod replace;
+mod macros;
+mod prefix;
+mod history;
+mod help;
+mod mode;
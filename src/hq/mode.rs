@@ -0,0 +1,313 @@
+use std::path::Path;
+use regex::Regex;
+use buf::{Buffer, CommentMarkers};
+
+/// A change made to the buffer before it is saved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaveHook {
+    DeleteTrailingWhitespace,
+    EnsureFinalNewline,
+}
+
+/// A major mode: the settings of the buffers by the file type.
+/// None keeps the setting given by the syntax or detected from the file.
+#[derive(Debug)]
+pub struct Mode {
+    pub name: &'static str,
+    /// The extensions, or the whole names such as `Makefile`.
+    pub files: &'static [&'static str],
+    /// The interpreters named by the shebang.
+    pub interpreters: &'static [&'static str],
+    /// The regular expression matching the first line.
+    pub first_line: Option<&'static str>,
+    pub tab_width: Option<usize>,
+    pub indent_tabs: Option<bool>,
+    pub comment: Option<CommentMarkers>,
+    pub word_chars: Option<&'static str>,
+    /// The bindings added to the mode keymap.
+    pub keys: &'static [(&'static str, &'static str)],
+    pub save_hooks: &'static [SaveHook],
+}
+
+/// The mode taken when nothing else matches.
+pub const FUNDAMENTAL: &'static str = "fundamental";
+
+const LINE_SLASHES: Option<CommentMarkers> = Some(CommentMarkers {
+    start: "// ",
+    end: "",
+});
+const LINE_HASH: Option<CommentMarkers> = Some(CommentMarkers {
+    start: "# ",
+    end: "",
+});
+
+const CLEAN_ON_SAVE: &'static [SaveHook] = &[
+    SaveHook::DeleteTrailingWhitespace,
+    SaveHook::EnsureFinalNewline,
+];
+
+const COMMENT_KEYS: &'static [(&'static str, &'static str)] = &[("C-c C-c", "comment-region")];
+
+const MODES: &'static [Mode] = &[
+    Mode {
+        name: FUNDAMENTAL,
+        files: &[],
+        interpreters: &[],
+        first_line: None,
+        tab_width: None,
+        indent_tabs: None,
+        comment: None,
+        word_chars: None,
+        keys: &[],
+        save_hooks: &[],
+    },
+    Mode {
+        name: "rust",
+        files: &["rs"],
+        interpreters: &[],
+        first_line: None,
+        tab_width: Some(4),
+        indent_tabs: Some(false),
+        comment: LINE_SLASHES,
+        word_chars: Some(""),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "python",
+        files: &["py", "pyw"],
+        interpreters: &["python", "python2", "python3"],
+        first_line: None,
+        tab_width: Some(4),
+        indent_tabs: Some(false),
+        comment: LINE_HASH,
+        word_chars: Some(""),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "shell",
+        files: &["sh", "bash", "zsh", ".bashrc", ".profile"],
+        interpreters: &["sh", "bash", "zsh", "dash"],
+        first_line: None,
+        tab_width: None,
+        indent_tabs: None,
+        comment: LINE_HASH,
+        word_chars: Some("-"),
+        keys: COMMENT_KEYS,
+        save_hooks: &[SaveHook::EnsureFinalNewline],
+    },
+    Mode {
+        name: "makefile",
+        files: &["Makefile", "makefile", "GNUmakefile", "mk"],
+        interpreters: &["make"],
+        first_line: None,
+        tab_width: Some(8),
+        indent_tabs: Some(true),
+        comment: LINE_HASH,
+        word_chars: Some("-"),
+        keys: COMMENT_KEYS,
+        save_hooks: &[SaveHook::EnsureFinalNewline],
+    },
+    Mode {
+        name: "c",
+        files: &["c", "h", "cc", "cpp", "hpp"],
+        interpreters: &[],
+        first_line: None,
+        tab_width: None,
+        indent_tabs: None,
+        comment: LINE_SLASHES,
+        word_chars: Some(""),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "go",
+        files: &["go"],
+        interpreters: &[],
+        first_line: None,
+        tab_width: Some(8),
+        indent_tabs: Some(true),
+        comment: LINE_SLASHES,
+        word_chars: Some(""),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "markdown",
+        files: &["md", "markdown"],
+        interpreters: &[],
+        first_line: None,
+        tab_width: Some(4),
+        indent_tabs: Some(false),
+        comment: None,
+        word_chars: None,
+        keys: &[],
+        // Trailing spaces break lines in Markdown.
+        save_hooks: &[SaveHook::EnsureFinalNewline],
+    },
+    Mode {
+        name: "yaml",
+        files: &["yml", "yaml"],
+        interpreters: &[],
+        first_line: Some(r"^%YAML"),
+        tab_width: Some(2),
+        indent_tabs: Some(false),
+        comment: LINE_HASH,
+        word_chars: Some("-"),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "toml",
+        files: &["toml", "Cargo.lock"],
+        interpreters: &[],
+        first_line: None,
+        tab_width: None,
+        indent_tabs: Some(false),
+        comment: LINE_HASH,
+        word_chars: Some("-"),
+        keys: COMMENT_KEYS,
+        save_hooks: CLEAN_ON_SAVE,
+    },
+    Mode {
+        name: "xml",
+        files: &["xml", "svg", "xsl"],
+        interpreters: &[],
+        first_line: Some(r"^<\?xml"),
+        tab_width: Some(2),
+        indent_tabs: None,
+        comment: Some(CommentMarkers {
+            start: "<!-- ",
+            end: " -->",
+        }),
+        word_chars: Some("-"),
+        keys: COMMENT_KEYS,
+        save_hooks: &[SaveHook::EnsureFinalNewline],
+    },
+];
+
+#[inline]
+pub fn modes() -> &'static [Mode] {
+    MODES
+}
+
+/// The names of the modes.
+pub fn names() -> Vec<&'static str> {
+    MODES.iter().map(|mode| mode.name).collect()
+}
+
+pub fn find(name: &str) -> Option<&'static Mode> {
+    MODES.iter().find(|mode| mode.name == name)
+}
+
+/// The mode named by `-*- mode: X -*-` in the first line.
+fn modeline(first_line: &str) -> Option<&'static Mode> {
+    let re = Regex::new(r"-\*-.*?\bmode:\s*([\w-]+).*-\*-").unwrap();
+    let name = re.captures(first_line)?.get(1)?.as_str().to_lowercase();
+    find(&name)
+}
+
+/// The interpreter of the shebang, e.g. `python3` for `#!/usr/bin/env python3`.
+fn interpreter(first_line: &str) -> Option<&str> {
+    if !first_line.starts_with("#!") {
+        return None;
+    }
+    let mut words = first_line[2..].split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        words.find(|word| !word.starts_with('-'))
+    } else {
+        Some(program)
+    }
+}
+
+/// Choose the mode by the modeline, the file name, the shebang and the first line, in order.
+pub fn detect(path: Option<&Path>, first_line: &str) -> &'static Mode {
+    if let Some(mode) = modeline(first_line) {
+        return mode;
+    }
+    if let Some(path) = path {
+        let file_name = path.file_name().and_then(|s| s.to_str());
+        let extension = path.extension().and_then(|s| s.to_str());
+        let mode = MODES.iter().find(|mode| {
+            mode.files.iter().any(|&s| Some(s) == file_name || Some(s) == extension)
+        });
+        if let Some(mode) = mode {
+            return mode;
+        }
+    }
+    if let Some(program) = interpreter(first_line) {
+        let mode = MODES.iter().find(|mode| mode.interpreters.iter().any(|&s| s == program));
+        if let Some(mode) = mode {
+            return mode;
+        }
+    }
+    MODES
+        .iter()
+        .find(|mode| {
+            mode.first_line.map_or(false, |re| {
+                Regex::new(re).unwrap().is_match(first_line)
+            })
+        })
+        .unwrap_or(&MODES[0])
+}
+
+impl Mode {
+    /// Set the mode and its settings to the buffer, over the ones given by the syntax
+    /// or detected from the file rather than the previous mode.
+    pub fn apply(&self, buf: &mut Buffer) {
+        buf.reset_settings();
+        buf.set_mode(self.name);
+        if let Some(n) = self.tab_width {
+            buf.set_tab_width(n);
+        }
+        if let Some(indent_tabs) = self.indent_tabs {
+            buf.set_indent_tabs(indent_tabs);
+        }
+        if let Some(comment) = self.comment {
+            buf.set_comment_markers(comment);
+        }
+        if let Some(word_chars) = self.word_chars {
+            buf.set_word_chars(word_chars);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util;
+
+    #[test]
+    fn test_detect() {
+        let name = |path: Option<&str>, first_line: &str| {
+            detect(path.map(Path::new), first_line).name
+        };
+        assert_eq!(name(Some("src/main.rs"), ""), "rust");
+        assert_eq!(name(Some("Makefile"), ""), "makefile");
+        assert_eq!(name(Some("build"), "#!/bin/bash -e"), "shell");
+        assert_eq!(name(Some("run"), "#!/usr/bin/env -S python3 -u"), "python");
+        assert_eq!(name(None, "<?xml version=\"1.0\"?>"), "xml");
+        assert_eq!(name(Some("a.txt"), "# -*- mode: Python -*-"), "python");
+        assert_eq!(name(Some("a.txt"), "hello"), FUNDAMENTAL);
+        assert!(find("go").unwrap().indent_tabs.unwrap());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut buf = Buffer::from_string("a\n");
+        find("makefile").unwrap().apply(&mut buf);
+        assert_eq!(buf.mode(), "makefile");
+        assert!(buf.indent_tabs());
+        assert_eq!(buf.comment_markers().unwrap().start, "# ");
+        // The settings of the previous mode are not kept.
+        find("python").unwrap().apply(&mut buf);
+        assert!(!buf.indent_tabs());
+        assert_eq!(buf.tab_width(), 4);
+        find(FUNDAMENTAL).unwrap().apply(&mut buf);
+        assert_eq!(buf.mode(), FUNDAMENTAL);
+        assert!(buf.comment_markers().is_none());
+        assert_eq!(buf.tab_width(), util::DEFAULT_TAB_WIDTH);
+    }
+}
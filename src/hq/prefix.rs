@@ -0,0 +1,82 @@
+use term;
+
+/// The numeric prefix argument typed by C-u or M-0..9.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Prefix {
+    /// The number of C-u typed before any digit.
+    universal: usize,
+    digits: Option<usize>,
+    /// C-u after the digits ends them, so that the next digit is typed.
+    closed: bool,
+}
+
+impl Prefix {
+    /// Update by the key. Return None if the key does not make up the prefix.
+    pub fn key(prefix: Option<Prefix>, k: term::Key) -> Option<Prefix> {
+        let active = prefix.is_some();
+        let prefix = prefix.unwrap_or_default();
+        match k {
+            term::Key::Ctrl('u') if prefix.digits.is_some() => Some(Prefix {
+                closed: true,
+                ..prefix
+            }),
+            term::Key::Ctrl('u') => Some(Prefix {
+                universal: prefix.universal + 1,
+                ..prefix
+            }),
+            term::Key::Meta(c) if c.is_digit(10) => Some(prefix.digit(c)),
+            term::Key::Char(c) if c.is_digit(10) && active && !prefix.closed => {
+                Some(prefix.digit(c))
+            }
+            _ => None,
+        }
+    }
+
+    fn digit(self, c: char) -> Prefix {
+        let d = c.to_digit(10).unwrap() as usize;
+        Prefix {
+            digits: Some(self.digits.unwrap_or(0).saturating_mul(10).saturating_add(d)),
+            closed: false,
+            ..self
+        }
+    }
+
+    /// The count: the digits, or 4 to the power of the number of C-u.
+    pub fn count(&self) -> usize {
+        match self.digits {
+            Some(n) => n,
+            None => (0..self.universal).fold(1, |acc: usize, _| acc.saturating_mul(4)),
+        }
+    }
+}
+
+impl ToString for Prefix {
+    /// Render as the echo of Emacs, e.g. `C-u 8-` or `C-u C-u-`.
+    fn to_string(&self) -> String {
+        match self.digits {
+            Some(n) => format!("C-u {}-", n),
+            None => format!("{}-", vec!["C-u"; self.universal].join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix() {
+        let keys = |keys: Vec<term::Key>| {
+            keys.into_iter().fold(None, |acc, k| Prefix::key(acc, k).or(acc))
+        };
+        let p = keys(vec![term::Key::Ctrl('u'), term::Key::Ctrl('u')]).unwrap();
+        assert_eq!((p.count(), p.to_string()), (16, String::from("C-u C-u-")));
+        let p = keys(vec![term::Key::Ctrl('u'), term::Key::Char('1'), term::Key::Char('2')]);
+        assert_eq!(p.unwrap().count(), 12);
+        let p = keys(vec![term::Key::Meta('3')]).unwrap();
+        assert_eq!((p.count(), p.to_string()), (3, String::from("C-u 3-")));
+        assert_eq!(Prefix::key(None, term::Key::Char('3')), None);
+        let p = keys(vec![term::Key::Meta('8'), term::Key::Ctrl('u')]);
+        assert_eq!(Prefix::key(p, term::Key::Char('5')), None);
+    }
+}
@@ -0,0 +1,165 @@
+use buf::{self, Buffer};
+use hq;
+use term;
+
+/// The state of `query-replace` and `replace-regexp`.
+pub struct Replace {
+    pattern: buf::Pattern,
+    from: String,
+    replacement: String,
+    current: Option<buf::Match>,
+    replaced: usize,
+}
+
+pub enum Status {
+    Continue(String),
+    Finished(usize),
+}
+
+impl Replace {
+    pub fn new(pattern: buf::Pattern, from: &str, replacement: &str) -> Replace {
+        Replace {
+            pattern: pattern,
+            from: String::from(from),
+            replacement: String::from(replacement),
+            current: None,
+            replaced: 0,
+        }
+    }
+
+    /// Find the next match from the position, and highlight it.
+    fn find(&mut self, buf: &mut Buffer, from: hq::Pair) -> Status {
+        self.current = self.pattern.find(&buf.lines(), from);
+        if let Some(ref m) = self.current {
+            buf.set_cursor(m.range.end.0, m.range.end.1);
+            buf.set_highlights(vec![m.range]);
+            return Status::Continue(format!(
+                "Query replacing {} with {}: (y, n, !, q)",
+                self.from,
+                self.replacement
+            ));
+        }
+        self.finish(buf)
+    }
+
+    /// The position to continue after the match. An empty match must go forward.
+    fn next_from(m: &buf::Match, end: hq::Pair) -> hq::Pair {
+        if m.is_empty() { (end.0 + 1, end.1) } else { end }
+    }
+
+    /// The text replacing the match.
+    fn replacement(&self, m: &buf::Match) -> String {
+        if self.pattern.is_regex() {
+            m.expand(&self.replacement)
+        } else {
+            self.replacement.clone()
+        }
+    }
+
+    /// Replace the current match.
+    fn replace(&mut self, buf: &mut Buffer) -> Option<hq::Pair> {
+        self.current.take().map(|m| {
+            let text = self.replacement(&m);
+            buf.replace_range(m.range, &text);
+            self.replaced += 1;
+            Replace::next_from(&m, buf.index_cursor())
+        })
+    }
+
+    /// Replace the current match and every one after it, and place the cursor after the last.
+    fn replace_all(&mut self, buf: &mut Buffer) {
+        let from = match self.current.take() {
+            Some(m) => m.range.start,
+            None => return,
+        };
+        let matches = self.pattern.matches_from(&buf.lines(), from);
+        // From the last, so that the ranges of the earlier ones stay valid.
+        // The cursor is kept as the distance from the end of the buffer.
+        let mut from_end = None;
+        for m in matches.iter().rev() {
+            let text = self.replacement(m);
+            buf.replace_range(m.range, &text);
+            self.replaced += 1;
+            if from_end.is_none() {
+                let (x, y) = buf.index_cursor();
+                let len = buf.get(y).map_or(0, |s| s.len());
+                from_end = Some((len - x, buf.line_num() - 1 - y));
+            }
+        }
+        if let Some((dx, dy)) = from_end {
+            let y = buf.line_num() - 1 - dy;
+            let len = buf.get(y).map_or(0, |s| s.len());
+            buf.set_cursor(len - dx, y);
+        }
+    }
+
+    /// Skip the current match.
+    fn skip(&mut self) -> Option<hq::Pair> {
+        self.current.take().map(
+            |m| Replace::next_from(&m, m.range.end),
+        )
+    }
+
+    /// End the session, clearing the highlights.
+    pub fn finish(&mut self, buf: &mut Buffer) -> Status {
+        self.current = None;
+        buf.set_highlights(vec![]);
+        Status::Finished(self.replaced)
+    }
+
+    /// Find the first match from the cursor.
+    pub fn start(&mut self, buf: &mut Buffer) -> Status {
+        let cursor = buf.index_cursor();
+        self.find(buf, cursor)
+    }
+
+    /// Handle the answer: y(or SPC) replaces, n(or DEL) skips, ! replaces all,
+    /// and q(or RET) quits.
+    pub fn answer(&mut self, buf: &mut Buffer, k: term::Key) -> Status {
+        let next = match k {
+            term::Key::Char('y') | term::Key::Char(' ') => self.replace(buf),
+            term::Key::Char('n') | term::Key::Del => self.skip(),
+            term::Key::Char('!') => {
+                self.replace_all(buf);
+                None
+            }
+            term::Key::Char('q') | term::Key::CR | term::Key::Esc => None,
+            _ => {
+                // Ask again.
+                let cursor = self.current.as_ref().map(|m| m.range.start);
+                return match cursor {
+                    Some(cursor) => self.find(buf, cursor),
+                    None => self.finish(buf),
+                };
+            }
+        };
+        match next {
+            Some(from) => self.find(buf, from),
+            None => self.finish(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_all() {
+        let mut buf = Buffer::from_string("a1 b2\nc3 d");
+        let options = buf::SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let pattern = buf::Pattern::new(r"(\w)(\d)", options).unwrap();
+        let mut replace = Replace::new(pattern, r"(\w)(\d)", "$2$1$1");
+        replace.start(&mut buf);
+        replace.answer(&mut buf, term::Key::Char('n'));
+        match replace.answer(&mut buf, term::Key::Char('!')) {
+            Status::Finished(n) => assert_eq!(n, 2),
+            Status::Continue(_) => panic!(),
+        }
+        assert_eq!(buf.to_str(), "a1 2bb\n3cc d\n");
+        assert_eq!(buf.index_cursor(), (3, 1));
+    }
+}
@@ -1,10 +1,21 @@
+use args;
+use hq;
 use ui;
 use std::collections::BTreeMap;
 use std::path::Path;
 use hq::fs::Filesys;
-use buf::Buffer;
+use hq::help::Help;
+use hq::keymap::{self, Keymaps, Layer};
+use hq::macros::Macros;
+use hq::mode::{self, SaveHook};
+use hq::replace::{self, Replace};
+use term;
+use buf::{self, Buffer};
 use util::ResultBox;
 
+/// Milliseconds before the keys continuing a key sequence are shown.
+const DEFAULT_WHICH_KEY_DELAY: usize = 1000;
+
 def_error! {
     NoElement: "no element.",
     NoFileName: "cannot infer filename.",
@@ -12,16 +23,44 @@ def_error! {
     InvalidFileName: "cannot decode the filename.",
 }
 
+/// The result of checking files on the disk.
+#[derive(Default)]
+pub struct Check {
+    /// The unmodified buffers reloaded from their files.
+    pub reverted: Vec<String>,
+    /// The modified current buffer whose file is changed.
+    pub conflict: Option<String>,
+}
+
 pub struct Workspace {
     buffers: BTreeMap<String, Buffer>,
+    current: String,
     fs: Filesys,
+    replace: Option<Replace>,
+    macros: Macros,
+    keymaps: Keymaps,
+    history: hq::Histories,
+    /// The count given by the prefix argument to the running command.
+    prefix: Option<usize>,
+    /// The help requested by the last command.
+    help: Option<Help>,
+    /// Milliseconds before the keys continuing a key sequence are shown.
+    which_key_delay: usize,
 }
 
 impl Workspace {
     pub fn new() -> ResultBox<Workspace> {
         let mut res = Workspace {
             buffers: BTreeMap::new(),
+            current: String::from("<empty>"),
             fs: Filesys::new()?,
+            replace: None,
+            macros: Macros::load(),
+            keymaps: Keymaps::new(),
+            history: hq::Histories::load(),
+            prefix: None,
+            help: None,
+            which_key_delay: DEFAULT_WHICH_KEY_DELAY,
         };
         // TODO: Refactor me!
         res.buffers.insert(
@@ -35,26 +74,700 @@ impl Workspace {
         &mut self.fs
     }
 
+    pub fn macros(&mut self) -> &mut Macros {
+        &mut self.macros
+    }
+
+    pub fn keymaps(&mut self) -> &mut Keymaps {
+        &mut self.keymaps
+    }
+
+    pub fn history(&mut self) -> &mut hq::Histories {
+        &mut self.history
+    }
+
+    /// The count of the prefix argument, if given.
+    #[inline]
+    pub fn prefix(&self) -> Option<usize> {
+        self.prefix
+    }
+
+    #[inline]
+    pub fn set_prefix(&mut self, value: Option<usize>) {
+        self.prefix = value;
+    }
+
+    #[inline]
+    pub fn which_key_delay(&self) -> usize {
+        self.which_key_delay
+    }
+
+    /// Take the help requested by the last command.
+    #[inline]
+    pub fn take_help(&mut self) -> Option<Help> {
+        self.help.take()
+    }
+
     pub fn buf(&mut self, s: &str) -> ResultBox<&mut Buffer> {
         self.buffers.get_mut(s).ok_or_else(
             || From::from(Error::NoElement),
         )
     }
 
+    /// Return the buffer shown in the focused editor.
+    pub fn cur_buf(&mut self) -> ResultBox<&mut Buffer> {
+        let current = self.current.clone();
+        self.buf(&current)
+    }
+
+    /// The name of the buffer shown in the focused editor.
+    pub fn cur_name(&self) -> String {
+        self.current.clone()
+    }
+
+    /// The names of the open buffers in order.
+    pub fn buffer_names(&self) -> Vec<String> {
+        self.buffers.keys().cloned().collect()
+    }
+
+    /// Set the name of the buffer shown in the focused editor.
+    pub fn set_current(&mut self, s: &str) {
+        self.current = String::from(s);
+    }
+
+    /// Open a file into a new buffer, and return the name of the buffer.
+    pub fn open_file(
+        &mut self,
+        path: &str,
+        cursor: Option<hq::Pair>,
+        read_only: bool,
+    ) -> ResultBox<String> {
+        let file_name = String::from(
+            Path::new(path)
+                .file_name()
+                .ok_or(Error::NoFileName)?
+                .to_str()
+                .ok_or(Error::InvalidFileName)?,
+        );
+        let mut buf = if Path::new(path).exists() {
+            Buffer::from_file(path)?
+        } else {
+            Buffer::new_file(path)
+        };
+        let first_line = buf.lines().into_iter().next().unwrap_or_default();
+        mode::detect(Some(Path::new(path)), &first_line).apply(&mut buf);
+        let (x, y) = cursor.unwrap_or((0, 0));
+        buf.set_cursor(x, y);
+        if read_only {
+            buf.set_read_only(true);
+        }
+        self.buffers.insert(file_name.clone(), buf);
+        Ok(file_name)
+    }
+
+    /// Open a string into a new buffer with the given name.
+    pub fn open_string(&mut self, name: &str, content: &str, read_only: bool) -> String {
+        let mut buf = Buffer::from_string(content);
+        let first_line = content.lines().next().unwrap_or("");
+        mode::detect(Some(Path::new(name)), first_line).apply(&mut buf);
+        buf.set_read_only(read_only);
+        self.buffers.insert(String::from(name), buf);
+        String::from(name)
+    }
+
     pub fn find_file(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
-        let s = &args[0];
-        let file_name = Path::new(s)
-            .file_name()
-            .ok_or(Error::NoFileName)?
-            .to_str()
-            .ok_or(Error::InvalidFileName)?;
-        let buf = Buffer::from_file(&s)?;
-        self.buffers.insert(String::from(file_name), buf);
-        self.buffers
-            .get_mut(file_name)
-            .ok_or(Error::Internal)?
-            .set_cursor(0, 0);
-        Ok(ui::Request::OpenBuffer(String::from(file_name)))
+        let buffer_name = self.open_file(&args[0], None, false)?;
+        Ok(ui::Request::OpenBuffer(buffer_name))
+    }
+
+    pub fn switch_to_buffer(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let name = String::from(args[0].trim());
+        self.buf(&name)?;
+        Ok(ui::Request::OpenBuffer(name))
+    }
+
+    pub fn goto_line(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        match args::parse_position(args[0].trim()) {
+            Ok((x, y)) => {
+                self.cur_buf()?.set_cursor(x, y);
+                Ok(ui::Request::OpenBuffer(self.current.clone()))
+            }
+            Err(_) => Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("Expected LINE or LINE:COL."),
+            ))),
+        }
+    }
+
+    pub fn toggle_read_only(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let buf = self.cur_buf()?;
+        let read_only = !buf.read_only();
+        buf.set_read_only(read_only);
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(String::from(
+            if read_only {
+                "Read-only mode enabled."
+            } else {
+                "Read-only mode disabled."
+            },
+        ))))
+    }
+
+    pub fn set_tab_width(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        match args[0].trim().parse::<usize>() {
+            Ok(n) if n > 0 => {
+                self.cur_buf()?.set_tab_width(n);
+                Ok(ui::Request::OpenBuffer(self.current.clone()))
+            }
+            _ => Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("Expected a positive number."),
+            ))),
+        }
+    }
+
+    pub fn toggle_indent_tabs(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let buf = self.cur_buf()?;
+        let indent_tabs = !buf.indent_tabs();
+        buf.set_indent_tabs(indent_tabs);
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(String::from(
+            if indent_tabs {
+                "Indent with tabs."
+            } else {
+                "Indent with spaces."
+            },
+        ))))
+    }
+
+    pub fn toggle_auto_indent(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let buf = self.cur_buf()?;
+        let auto_indent = !buf.auto_indent();
+        buf.set_auto_indent(auto_indent);
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(String::from(
+            if auto_indent {
+                "Auto indent enabled."
+            } else {
+                "Auto indent disabled."
+            },
+        ))))
+    }
+
+    pub fn newline_and_indent(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        {
+            let buf = self.cur_buf()?;
+            if buf.read_only() {
+                return Ok(ui::Request::CommandBar(
+                    ui::CommandBar::Notify(String::from("Buffer is read-only.")),
+                ));
+            }
+            buf.checkpoint();
+            buf.newline_and_indent();
+        }
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn set_mark(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.cur_buf()?.set_mark();
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(String::from("Mark set.")),
+        ))
+    }
+
+    pub fn jump_to_matching_bracket(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        if self.cur_buf()?.jump_to_matching_bracket() {
+            Ok(ui::Request::OpenBuffer(self.current.clone()))
+        } else {
+            Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("No matching bracket.")),
+            ))
+        }
+    }
+
+    /// Toggle the comment of the lines.
+    fn comment_lines(&mut self, region: bool) -> ResultBox<ui::Request> {
+        {
+            let buf = self.cur_buf()?;
+            if buf.read_only() {
+                return Ok(ui::Request::CommandBar(
+                    ui::CommandBar::Notify(String::from("Buffer is read-only.")),
+                ));
+            }
+            let (from, to) = if region {
+                buf.region_lines()
+            } else {
+                (buf.y(), buf.y())
+            };
+            if buf.comment_markers().is_none() {
+                return Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                    String::from("No comment syntax for this buffer."),
+                )));
+            }
+            buf.checkpoint();
+            buf.toggle_comment(from, to);
+            buf.clear_mark();
+        }
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn comment_line(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.comment_lines(false)
+    }
+
+    /// Comment the region, or the current line if the mark is not set.
+    pub fn comment_region(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.comment_lines(true)
+    }
+
+    /// Indent or outdent the lines between the mark and the cursor.
+    fn shift_region(&mut self, outdent: bool) -> ResultBox<ui::Request> {
+        {
+            let buf = self.cur_buf()?;
+            if buf.read_only() {
+                return Ok(ui::Request::CommandBar(
+                    ui::CommandBar::Notify(String::from("Buffer is read-only.")),
+                ));
+            }
+            let (from, to) = buf.region_lines();
+            buf.checkpoint();
+            if outdent {
+                buf.outdent_lines(from, to);
+            } else {
+                buf.indent_lines(from, to);
+            }
+        }
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn indent_region(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.shift_region(false)
+    }
+
+    pub fn outdent_region(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.shift_region(true)
+    }
+
+    /// Check every file on the disk. Unmodified buffers are reverted silently,
+    /// and the modified current buffer is reported as a conflict.
+    pub fn check_files(&mut self) -> Check {
+        let mut res: Check = Default::default();
+        for (name, buf) in self.buffers.iter_mut() {
+            let current = *name == self.current;
+            if (buf.modified() && !current) || !buf.check_file() {
+                continue;
+            }
+            if buf.modified() {
+                res.conflict = Some(name.clone());
+            } else if buf.revert().is_ok() {
+                res.reverted.push(name.clone());
+            }
+        }
+        res
+    }
+
+    /// Write every modified buffer into its recovery file.
+    pub fn auto_save(&mut self) {
+        for buf in self.buffers.values_mut() {
+            // A buffer without file cannot be recovered.
+            let _ = buf.auto_save();
+        }
+    }
+
+    pub fn recover_file(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        if args[0].starts_with('y') {
+            self.cur_buf()?.recover()?;
+        }
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    /// Apply the format to the current buffer, and report the result.
+    fn convert(&mut self, format: buf::Format) -> ResultBox<ui::Request> {
+        let msg = match self.cur_buf()?.set_format(format) {
+            Ok(()) => format!("The buffer will be saved in {}.", format),
+            Err(e) => format!("Cannot convert: {}", e),
+        };
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(msg)))
+    }
+
+    pub fn set_encoding(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let format = self.cur_buf()?.format();
+        match buf::Encoding::from_name(&args[0]) {
+            Some(encoding) => self.convert(buf::Format {
+                encoding: encoding,
+                ..format
+            }),
+            None => Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("Unknown encoding.")),
+            )),
+        }
+    }
+
+    pub fn set_line_ending(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let format = self.cur_buf()?.format();
+        match buf::LineEnding::from_name(&args[0]) {
+            Some(line_ending) => self.convert(buf::Format {
+                line_ending: line_ending,
+                ..format
+            }),
+            None => Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("Unknown line ending.")),
+            )),
+        }
+    }
+
+    /// Compile the pattern, or return the notice of the error.
+    fn pattern(
+        s: &str,
+        options: buf::SearchOptions,
+    ) -> ::std::result::Result<buf::Pattern, ui::Request> {
+        buf::Pattern::new(s, options).map_err(|e| {
+            ui::Request::CommandBar(ui::CommandBar::Notify(format!("Invalid regexp: {}", e)))
+        })
+    }
+
+    /// Highlight every match, and move the cursor to the end of the next match.
+    fn search(&mut self, s: &str, options: buf::SearchOptions) -> ResultBox<ui::Request> {
+        let pattern = match Workspace::pattern(s, options) {
+            Ok(pattern) => pattern,
+            Err(req) => return Ok(req),
+        };
+        let buf = self.cur_buf()?;
+        let lines = buf.lines();
+        let ranges = pattern.find_all(&lines);
+        let cursor = buf.index_cursor();
+        let (found, wrapped) = match pattern.find(&lines, cursor) {
+            Some(m) => (Some(m), false),
+            None => (pattern.find(&lines, (0, 0)), true),
+        };
+        let failed = found.is_none();
+        let msg = match found {
+            Some(m) => {
+                buf.set_cursor(m.range.end.0, m.range.end.1);
+                format!(
+                    "{}{} match(es) for {}.",
+                    if wrapped { "Wrapped: " } else { "" },
+                    ranges.len(),
+                    s
+                )
+            }
+            None => format!("Search failed: {}", s),
+        };
+        buf.set_highlights(ranges);
+        if failed {
+            self.macros.fail();
+        }
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(msg)))
+    }
+
+    pub fn search_forward(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.search(
+            &args[0],
+            buf::SearchOptions {
+                smart_case: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn search_regexp(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.search(
+            &args[0],
+            buf::SearchOptions {
+                regex: true,
+                smart_case: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn search_word(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.search(
+            &args[0],
+            buf::SearchOptions {
+                smart_case: true,
+                whole_word: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Convert the status of the replace session into the request.
+    fn replace_status(&mut self, status: replace::Status) -> ui::Request {
+        let msg = match status {
+            replace::Status::Continue(msg) => msg,
+            replace::Status::Finished(n) => {
+                self.replace = None;
+                format!("Replaced {} occurrence(s).", n)
+            }
+        };
+        ui::Request::CommandBar(ui::CommandBar::Notify(msg))
+    }
+
+    /// Start the replace session as a single undoable unit.
+    fn start_replace(&mut self, args: Vec<String>, is_regex: bool) -> ResultBox<ui::Request> {
+        let options = buf::SearchOptions {
+            regex: is_regex,
+            smart_case: true,
+            ..Default::default()
+        };
+        let pattern = match Workspace::pattern(&args[0], options) {
+            Ok(pattern) => pattern,
+            Err(req) => return Ok(req),
+        };
+        let mut replace = Replace::new(pattern, &args[0], &args[1]);
+        let status = {
+            let buf = self.cur_buf()?;
+            if buf.read_only() {
+                return Ok(ui::Request::CommandBar(
+                    ui::CommandBar::Notify(String::from("Buffer is read-only.")),
+                ));
+            }
+            buf.checkpoint();
+            replace.start(buf)
+        };
+        self.replace = Some(replace);
+        Ok(self.replace_status(status))
+    }
+
+    /// Handle the answer of the replace session.
+    /// Return None iff there is no replace session.
+    pub fn on_replace_key(&mut self, k: term::Key) -> ResultBox<Option<ui::Request>> {
+        let mut replace = match self.replace.take() {
+            Some(replace) => replace,
+            None => return Ok(None),
+        };
+        let status = replace.answer(self.cur_buf()?, k);
+        self.replace = Some(replace);
+        Ok(Some(self.replace_status(status)))
+    }
+
+    pub fn query_replace(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.start_replace(args, false)
+    }
+
+    pub fn replace_regexp(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.start_replace(args, true)
+    }
+
+    pub fn undo(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let times = self.prefix.unwrap_or(1);
+        let buf = self.cur_buf()?;
+        if (0..times).fold(false, |undone, _| buf.undo() || undone) {
+            Ok(ui::Request::OpenBuffer(self.current.clone()))
+        } else {
+            Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("No further undo information."),
+            )))
+        }
+    }
+
+    pub fn save_buffer(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        // The save hooks would change the buffer which cannot be saved.
+        if self.cur_buf()?.read_only() {
+            return Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("Buffer is read-only.")),
+            ));
+        }
+        if !self.cur_buf()?.parent_exists() {
+            return Ok(ui::Request::Command(String::from("save-buffer-creating-directories")));
+        }
+        self.run_save_hooks()?;
+        self.cur_buf()?.save()?;
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(format!("Wrote {}.", self.current)),
+        ))
+    }
+
+    /// Run the save hooks of the mode of the current buffer.
+    fn run_save_hooks(&mut self) -> ResultBox<()> {
+        let buf = self.cur_buf()?;
+        let hooks = mode::find(buf.mode()).map_or(&[][..], |mode| mode.save_hooks);
+        for hook in hooks {
+            match *hook {
+                SaveHook::DeleteTrailingWhitespace => {
+                    buf.delete_trailing_whitespace();
+                }
+                SaveHook::EnsureFinalNewline => {
+                    let format = buf.format();
+                    if !format.final_newline {
+                        buf.set_format(buf::Format {
+                            final_newline: true,
+                            ..format
+                        })?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_buffer_creating_directories(
+        &mut self,
+        args: Vec<String>,
+    ) -> ResultBox<ui::Request> {
+        if !args[0].starts_with('y') {
+            return Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("Not saved.")),
+            ));
+        }
+        self.cur_buf()?.create_parent()?;
+        self.save_buffer(vec![])
+    }
+
+    pub fn revert_buffer(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        if args[0].starts_with('y') {
+            self.cur_buf()?.revert()?;
+        }
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn start_macro(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let msg = if self.macros.start() {
+            "Defining keyboard macro..."
+        } else {
+            "Already defining keyboard macro."
+        };
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(String::from(msg)),
+        ))
+    }
+
+    pub fn end_macro(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let msg = match self.macros.end() {
+            Some(0) => String::from("Ignore empty macro."),
+            Some(n) => format!("Keyboard macro defined ({} keys).", n),
+            None => String::from("Not defining keyboard macro."),
+        };
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(msg)))
+    }
+
+    /// Request the handler to replay the macro.
+    fn play_macro(&mut self, name: Option<&str>, times: usize) -> ResultBox<ui::Request> {
+        if self.macros.recording() {
+            return Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("Cannot call a macro while defining one."),
+            )));
+        }
+        if self.macros.play(name, times) {
+            Ok(ui::Request::OpenBuffer(self.current.clone()))
+        } else {
+            Ok(ui::Request::CommandBar(
+                ui::CommandBar::Notify(String::from("No such keyboard macro.")),
+            ))
+        }
+    }
+
+    pub fn call_macro(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        let times = self.prefix.unwrap_or(1);
+        self.play_macro(None, times)
+    }
+
+    pub fn repeat_macro(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        match args[0].trim().parse::<usize>() {
+            Ok(n) if n > 0 => self.play_macro(None, n),
+            _ => Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("Expected a positive number."),
+            ))),
+        }
+    }
+
+    pub fn call_named_macro(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.play_macro(Some(args[0].trim()), 1)
+    }
+
+    /// Name the last macro, and save it in the configuration directory.
+    pub fn name_last_macro(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let name = args[0].trim();
+        let msg = if name.is_empty() || name.contains(char::is_whitespace) {
+            String::from("Invalid macro name.")
+        } else if self.macros.name_last(name)? {
+            format!("Saved keyboard macro {}.", name)
+        } else {
+            String::from("No keyboard macro defined.")
+        };
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(msg)))
+    }
+
+    /// Cancel the replace session, the macro being recorded, the search highlights and the mark.
+    pub fn keyboard_quit(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.macros.fail();
+        self.macros.abort();
+        self.prefix = None;
+        self.help = None;
+        let replace = self.replace.take();
+        let buf = self.cur_buf()?;
+        if let Some(mut replace) = replace {
+            replace.finish(buf);
+        }
+        buf.set_highlights(vec![]);
+        buf.clear_mark();
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(String::from("Quit")),
+        ))
+    }
+
+    pub fn describe_key(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.help = Some(Help::Key);
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(String::from("Describe key: ")),
+        ))
+    }
+
+    pub fn describe_command(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        self.help = Some(Help::Command(String::from(args[0].trim())));
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn list_keybindings(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
+        self.help = Some(Help::Keybindings);
+        Ok(ui::Request::OpenBuffer(self.current.clone()))
+    }
+
+    pub fn set_which_key_delay(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        match args[0].trim().parse::<usize>() {
+            Ok(n) => {
+                self.which_key_delay = n;
+                Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                    format!("Key hints are shown after {}ms.", n),
+                )))
+            }
+            _ => Ok(ui::Request::CommandBar(ui::CommandBar::Notify(
+                String::from("Expected a non-negative number."),
+            ))),
+        }
+    }
+
+    /// The major mode of the current buffer, if any.
+    pub fn cur_mode(&self) -> Option<&str> {
+        match self.buffers.get(&self.current) {
+            Some(buf) if !buf.mode().is_empty() => Some(buf.mode()),
+            _ => None,
+        }
+    }
+
+    pub fn set_mode(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let mode = mode::find(args[0].trim()).ok_or(Error::Internal)?;
+        mode.apply(self.cur_buf()?);
+        Ok(ui::Request::CommandBar(
+            ui::CommandBar::Notify(format!("Major mode: {}.", mode.name)),
+        ))
+    }
+
+    pub fn global_set_key(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let keys = keymap::parse_keys(&args[0]).ok_or(Error::Internal)?;
+        let name = args[1].trim();
+        self.keymaps.bind(&Layer::Global, &keys, name);
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(format!(
+            "{} runs the command {}.",
+            keymap::keys_to_string(&keys),
+            name
+        ))))
+    }
+
+    pub fn global_unset_key(&mut self, args: Vec<String>) -> ResultBox<ui::Request> {
+        let keys = keymap::parse_keys(&args[0]).ok_or(Error::Internal)?;
+        let msg = if self.keymaps.unbind(&Layer::Global, &keys) {
+            format!("{} is unbound.", keymap::keys_to_string(&keys))
+        } else {
+            format!("{} is not bound.", keymap::keys_to_string(&keys))
+        };
+        Ok(ui::Request::CommandBar(ui::CommandBar::Notify(msg)))
     }
 
     pub fn quit(&mut self, _: Vec<String>) -> ResultBox<ui::Request> {
@@ -1,9 +1,10 @@
 use std::convert::From;
+use std::panic;
 use libc;
 
 use term;
 use hq;
-use io::kqueue::Kqueue;
+use io::kqueue::{Kqueue, TIMER_IDENT};
 use io::term::Term;
 use util::ResultBox;
 
@@ -57,9 +58,15 @@ impl Handler {
         Ok(())
     }
 
-    /// Handle result from the Hq.
+    /// Send the event to the Hq.
     fn handle_event(&mut self, e: hq::Request) -> ResultBox<()> {
-        match self.hq.request(e)? {
+        let res = self.hq.request(e)?;
+        self.handle_response(res)
+    }
+
+    /// Handle result from the Hq.
+    fn handle_response(&mut self, res: hq::Response) -> ResultBox<()> {
+        match res {
             //hq::Response::Command(c) => self.hq.call(&c),
             hq::Response::Term { refresh, cursor } => {
                 self.term.show_cursor(false);
@@ -80,6 +87,12 @@ impl Handler {
         Ok(())
     }
 
+    /// Handle the timer event.
+    fn handle_timer(&mut self) -> ResultBox<()> {
+        let res = self.hq.tick()?;
+        self.handle_response(res)
+    }
+
     // Handle resize event of terminal.
     fn handle_sigwinch(&mut self) -> ResultBox<()> {
         let (w, h) = self.term.get_size()?;
@@ -91,13 +104,22 @@ impl Handler {
             libc::STDOUT_FILENO => self.handle_stdout(),
             libc::STDIN_FILENO => self.handle_stdin(),
             libc::SIGWINCH => self.handle_sigwinch(),
+            TIMER_IDENT => self.handle_timer(),
             _ => Ok(()),
         }
     }
 
     pub fn run(&mut self) -> ResultBox<()> {
-        let mut kqueue = Kqueue::new()?;
-        kqueue.init()?;
-        kqueue.kevent(self)
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| -> ResultBox<()> {
+            let mut kqueue = Kqueue::new()?;
+            kqueue.init()?;
+            kqueue.kevent(self)
+        }));
+        // Keep unsaved works whenever the loop ends, even by panic.
+        self.hq.auto_save();
+        match res {
+            Ok(res) => res,
+            Err(e) => panic::resume_unwind(e),
+        }
     }
 }
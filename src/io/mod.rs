@@ -3,3 +3,4 @@ mod term;
 mod kqueue;
 
 pub use io::handler::Handler;
+pub use io::term::attach_tty;
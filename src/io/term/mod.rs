@@ -15,6 +15,24 @@ def_error! {
     Read: "read returned -1",
     FGetfl: "fcntl(F_GETFL) returned -1",
     FSetfl: "fcntl(F_SETFL) returned -1",
+    Open: "open(/dev/tty) returned -1",
+    Dup2: "dup2 returned -1",
+}
+
+/// Replace STDIN with the controlling terminal.
+/// This is required after STDIN has been consumed as the content of a buffer.
+pub fn attach_tty() -> Result<()> {
+    unsafe {
+        let fd = libc::open("/dev/tty\0".as_ptr() as *const i8, libc::O_RDWR);
+        if fd == -1 {
+            return Err(Error::Open);
+        }
+        if libc::dup2(fd, libc::STDIN_FILENO) == -1 {
+            return Err(Error::Dup2);
+        }
+        libc::close(fd);
+    }
+    Ok(())
 }
 
 #[derive(Default)]
@@ -93,7 +111,7 @@ impl Term {
                 self.brush = prev;
             }
             // TODO: Optimize
-            self.write(&c.chr.to_string());
+            self.write(&c.to_string());
         }
     }
 
@@ -3,24 +3,65 @@
 extern crate libc;
 #[macro_use]
 extern crate proc_macros;
+extern crate regex;
 extern crate syntect;
+extern crate unicode_segmentation;
+extern crate unicode_width;
 
 #[macro_use]
 mod util;
+mod args;
 mod buf;
 mod io;
 mod ui;
 mod hq;
 mod term;
 
+use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::process;
+use util::ResultBox;
+
+/// Read STDIN and open the files given from the command line.
+/// This must be done before the terminal turns into the raw mode.
+fn open_args(args: &args::Args, hq_handler: &mut hq::Handler) -> ResultBox<()> {
+    if args.stdin {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        io::attach_tty()?;
+        hq_handler.open_string("<stdin>", &content, args.read_only)?;
+    }
+    for file in &args.files {
+        hq_handler.open_file(&file.path, file.cursor, args.read_only)?;
+    }
+    Ok(())
+}
 
 /// The main function
 /// `io::Handler` => `hq::Handler` => `ui::Screen`
 fn main() {
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(args::Action::Run(args)) => args,
+        Ok(args::Action::Help) => {
+            print!("{}", args::USAGE);
+            return;
+        }
+        Ok(args::Action::Version) => {
+            println!("thief {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(e) => {
+            eprint!("thief: {}\n{}", e, args::USAGE);
+            process::exit(2);
+        }
+    };
     let screen = ui::Screen::new().unwrap();
-    let hq_handler = hq::Handler::new(screen).unwrap();
+    let mut hq_handler = hq::Handler::new(screen).unwrap();
+    if let Err(e) = open_args(&args, &mut hq_handler) {
+        eprintln!("thief: {}", e);
+        process::exit(1);
+    }
     let mut io_handler = io::Handler::new(hq_handler).unwrap();
     match io_handler.run() {
         Ok(()) => (),
@@ -6,23 +6,57 @@ use std::fmt;
 pub struct Char {
     pub chr: char,
     pub brush: term::Brush,
+    /// Combining characters drawn together in the cell, padded with `'\0'`.
+    /// Kept inline so that a cell stays small; further marks are dropped.
+    combining: [char; 2],
+    /// True iff in a string or a comment, where brackets are not paired.
+    pub literal: bool,
 }
 
 impl fmt::Display for Char {
-    /// Write the character.
+    /// Write the character. The tail of a wide character is not written.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.chr)
+        if self.is_tail() {
+            return Ok(());
+        }
+        write!(f, "{}", self.chr)?;
+        for c in self.combining.iter().take_while(|&&c| c != '\0') {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
     }
 }
 
 impl Char {
+    /// The placeholder of the cell covered by the preceding wide character.
+    pub const TAIL: char = '\0';
+
     pub fn new(chr: char, brush: term::Brush) -> Char {
         Char {
             chr: chr,
             brush: brush,
+            combining: ['\0'; 2],
+            literal: false,
+        }
+    }
+
+    /// Draw the combining character together, if there is room.
+    pub fn push_combining(&mut self, c: char) {
+        if let Some(p) = self.combining.iter_mut().find(|p| **p == '\0') {
+            *p = c;
         }
     }
 
+    #[inline]
+    pub fn clear_combining(&mut self) {
+        self.combining = ['\0'; 2];
+    }
+
+    #[inline]
+    pub fn is_tail(&self) -> bool {
+        self.chr == Char::TAIL
+    }
+
     /// Discard color and return the char.
     #[cfg(test)]
     pub fn chr(&self) -> char {
@@ -36,6 +70,7 @@ impl Char {
     pub fn overwrite(&mut self, s: term::Style, c: char) {
         use term::Style::{File, Directory};
         self.chr = c;
+        self.clear_combining();
         self.brush.fg = match s {
             File => term::Color::new(200, 100, 100),
             Directory => term::Color::new(100, 100, 200),
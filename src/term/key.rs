@@ -8,6 +8,7 @@ pub enum Key {
     End,
     CR,
     LF,
+    Tab,
     Del,
     Up,
     Down,
@@ -21,18 +22,110 @@ impl Key {
         match self {
             Key::Ctrl('j') => Key::LF,
             Key::Ctrl('m') => Key::CR,
+            Key::Ctrl('i') => Key::Tab,
             Key::Char('\x7f') => Key::Del,
             etc => etc,
         }
     }
+
+    /// Parse the key written by `to_string`, e.g. `C-x`, `M-f`, `RET` or `a`.
+    pub fn parse(s: &str) -> Option<Key> {
+        let named = |s: &str| match s {
+            "SPC" => Some(' '),
+            "DEL" => Some('\x7f'),
+            _ => None,
+        };
+        let single = |s: &str| {
+            let mut it = s.chars();
+            match (it.next(), it.next()) {
+                (Some(c), None) => Some(c),
+                _ => named(s),
+            }
+        };
+        let key = match s {
+            "ESC" => Key::Esc,
+            "RET" => Key::CR,
+            "TAB" => Key::Tab,
+            "DEL" => Key::Del,
+            "C-_" => Key::Char('\u{1f}'),
+            "<home>" => Key::Home,
+            "<end>" => Key::End,
+            "<up>" => Key::Up,
+            "<down>" => Key::Down,
+            "<left>" => Key::Left,
+            "<right>" => Key::Right,
+            _ if s.starts_with("C-") => Key::Ctrl(single(&s[2..])?),
+            _ if s.starts_with("M-") => Key::Meta(single(&s[2..])?),
+            _ => Key::Char(single(s)?),
+        };
+        Some(key.normalize())
+    }
 }
 
 impl ToString for Key {
+    /// Render in the Emacs notation, e.g. `C-x`, `M-f` or `RET`.
     fn to_string(&self) -> String {
-        String::from(match *self {
-            Key::Char('a') => "C-a",
-            Key::Char('b') => "C-b",
-            _ => "unknown",
-        })
+        let named = |c: char| match c {
+            ' ' => String::from("SPC"),
+            '\x7f' => String::from("DEL"),
+            c => c.to_string(),
+        };
+        match *self {
+            Key::Char('\u{1f}') => String::from("C-_"),
+            Key::Char(c) => named(c),
+            Key::Ctrl(c) => format!("C-{}", named(c)),
+            Key::Meta(c) => format!("M-{}", named(c)),
+            Key::Esc => String::from("ESC"),
+            Key::Home => String::from("<home>"),
+            Key::End => String::from("<end>"),
+            Key::CR => String::from("RET"),
+            Key::LF => String::from("C-j"),
+            Key::Tab => String::from("TAB"),
+            Key::Del => String::from("DEL"),
+            Key::Up => String::from("<up>"),
+            Key::Down => String::from("<down>"),
+            Key::Left => String::from("<left>"),
+            Key::Right => String::from("<right>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Key::parse("a"), Some(Key::Char('a')));
+        assert_eq!(Key::parse("SPC"), Some(Key::Char(' ')));
+        assert_eq!(Key::parse("C-_"), Some(Key::Char('\u{1f}')));
+        assert_eq!(Key::parse("C-x"), Some(Key::Ctrl('x')));
+        assert_eq!(Key::parse("C-m"), Some(Key::CR));
+        assert_eq!(Key::parse("M-DEL"), Some(Key::Meta('\x7f')));
+        assert_eq!(Key::parse("<up>"), Some(Key::Up));
+        assert_eq!(Key::parse("C-"), None);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let keys = vec![
+            Key::Char('a'),
+            Key::Char(' '),
+            Key::Char('\u{1f}'),
+            Key::Ctrl('x'),
+            Key::Ctrl('@'),
+            Key::Meta('%'),
+            Key::Meta('\x7f'),
+            Key::CR,
+            Key::LF,
+            Key::Tab,
+            Key::Del,
+            Key::Up,
+        ];
+        for k in keys {
+            assert_eq!(Key::parse(&k.to_string()), Some(k));
+        }
+        assert_eq!(Key::Ctrl('f').to_string(), "C-f");
+        assert_eq!(Key::Meta('\x7f').to_string(), "M-DEL");
     }
 }
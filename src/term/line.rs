@@ -1,4 +1,6 @@
 use term;
+use util;
+use std::cmp;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -95,28 +97,81 @@ impl Line {
 
     /// Write a singl character with a color.
     fn write_char(&mut self, location: usize, symbol: char, color: term::Color) {
+        if self.chars[location].is_tail() && location > 0 {
+            // Do not leave the half of a wide glyph.
+            self.chars[location - 1].chr = ' ';
+        }
         let p = &mut self.chars[location];
         p.chr = symbol;
+        p.clear_combining();
         p.brush.fg = color;
     }
 
+    /// Put the character at the column `col` in the columns `[start, end)`.
+    /// A wide character takes the next cell as its tail, and a combining character
+    /// joins the previous cell. Return the next column, or None if it does not fit.
+    fn put(
+        &mut self,
+        (start, end): (usize, usize),
+        col: usize,
+        c: char,
+        fg: Option<term::Color>,
+    ) -> Option<usize> {
+        let w = util::term_width(c);
+        if w == 0 {
+            if let Some(p) = self.chars[start..col].iter_mut().rev().find(|p| !p.is_tail()) {
+                p.push_combining(c);
+            }
+            return Some(col);
+        }
+        if col + w > end {
+            // Never split a wide glyph at the edge.
+            for p in &mut self.chars[col..end] {
+                p.chr = ' ';
+                p.clear_combining();
+            }
+            return None;
+        }
+        // Do not leave the half of a wide glyph being overwritten.
+        if self.chars[col].is_tail() && col > 0 {
+            self.chars[col - 1].chr = ' ';
+        }
+        if self.chars.get(col + w).map_or(false, |p| p.is_tail()) {
+            self.chars[col + w].chr = ' ';
+        }
+        for (i, p) in self.chars[col..col + w].iter_mut().enumerate() {
+            p.chr = if i == 0 { c } else { term::Char::TAIL };
+            p.clear_combining();
+            if let Some(fg) = fg {
+                p.brush.fg = fg;
+            }
+        }
+        Some(col + w)
+    }
+
     /// Draw the given string into here.
-    ///
-    /// TODO: Process full-width characters.
     pub fn draw_str(&mut self, src: &str, x: usize, limit: usize) {
-        let limit_x = if limit == 0 { self.width - x } else { limit };
-        for (i, c) in src.chars().enumerate() {
-            if i >= limit_x {
-                break;
-            };
-            self.chars[x + i].chr = c;
+        let end = if limit == 0 { self.width } else { cmp::min(x + limit, self.width) };
+        let mut col = x;
+        for c in src.chars() {
+            match self.put((x, end), col, c, None) {
+                Some(next) => col = next,
+                None => break,
+            }
+        }
+    }
+
+    /// Paint the background of the columns `[from, to)`.
+    pub fn highlight(&mut self, from: usize, to: usize, bg: term::Color) {
+        for i in from..to {
+            if i < self.width {
+                self.chars[i].brush.bg = bg;
+            }
         }
     }
 
     /// Draw the given string into line_editor.
     /// Return true iff there is more string on the right.
-    ///
-    /// TODO: Process full-width characters.
     pub fn draw_str_ex(
         &mut self,
         src: &term::String,
@@ -125,20 +180,44 @@ impl Line {
         color_arrow: term::Color,
     ) -> bool {
         let mut limit_x = if limit == 0 { self.width - x } else { limit };
-        let more_right = limit_x < src.len();
+        let more_right = limit_x < src.width();
         if more_right {
             limit_x -= 1;
             let w = self.width;
             self.write_char(w - 1, '>', color_arrow);
         }
-        for (i, c) in src.iter().enumerate() {
-            if i >= limit_x {
-                break;
-            };
-            // TODO: Make clear.
-            self.chars[x + i].chr = c.chr;
-            self.chars[x + i].brush.fg = c.brush.fg
+        let mut col = x;
+        for c in src.iter() {
+            match self.put((x, x + limit_x), col, c.chr, Some(c.brush.fg)) {
+                Some(next) => col = next,
+                None => break,
+            }
         }
         more_right
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_wide() {
+        let brush = term::Brush::black_and_white();
+        let mut line = Line::new(5, brush);
+        let more_right = line.draw_str_ex(
+            &term::String::from_std("ab한글", brush),
+            0,
+            0,
+            term::Color::white(),
+        );
+        assert!(more_right);
+        // The glyph at the right edge is not split.
+        assert_eq!(format!("{}", line), "ab한>");
+        let mut line = Line::new(3, brush);
+        line.draw_str("e\u{301}한", 0, 0);
+        assert_eq!(format!("{}", line), "e\u{301}한");
+        line.draw_str("x", 2, 0);
+        assert_eq!(format!("{}", line), "e\u{301} x");
+    }
+}
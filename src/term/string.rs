@@ -1,5 +1,6 @@
 use std;
 use term;
+use util;
 
 #[derive(Debug, Clone)]
 pub struct String {
@@ -29,6 +30,29 @@ impl String {
         Self { vec: self.vec.split_off(n) }
     }
 
+    /// Skip the first n columns. A wide character across the boundary becomes a space.
+    pub fn skip_columns(self, n: usize) -> Self {
+        let mut res = Self::new();
+        let mut x = 0;
+        for c in self.vec {
+            let next = x + c.width();
+            if x >= n {
+                res.push(c);
+            } else if next > n {
+                for _ in n..next {
+                    res.push(term::Char::new(' ', c.brush));
+                }
+            }
+            x = next;
+        }
+        res
+    }
+
+    /// The width on the terminal.
+    pub fn width(&self) -> usize {
+        self.iter().map(|c| c.width()).sum()
+    }
+
     /// Length of the vector.
     pub fn len(&self) -> usize {
         self.vec.len()
@@ -69,13 +93,65 @@ impl String {
         self.vec.iter()
     }
 
+    #[cfg(test)]
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<term::Char> {
+        self.vec.iter_mut()
+    }
+
     /// Check if empty.
     pub fn is_empty(&self) -> bool {
         self.vec.is_empty()
     }
 
-    #[cfg(test)]
+    /// The column of the character at the index, expanding tabs.
+    pub fn column(&self, index: usize, tab_width: usize) -> usize {
+        self.iter().take(index).fold(
+            0,
+            |x, c| util::advance(x, c.chr, tab_width),
+        )
+    }
+
+    /// Replace tabs by spaces up to the next tab stop.
+    pub fn expand_tabs(&self, tab_width: usize) -> Self {
+        let mut res = Self::new();
+        let mut x = 0;
+        for c in self.iter() {
+            let next = util::advance(x, c.chr, tab_width);
+            if c.chr == '\t' {
+                for _ in x..next {
+                    res.push(term::Char::new(' ', c.brush));
+                }
+            } else {
+                res.push(c.clone());
+            }
+            x = next;
+        }
+        res
+    }
+
+    /// Discard colors and return the std string.
     pub fn to_str(&self) -> std::string::String {
-        self.iter().map(|c|c.chr).collect()
+        self.iter().map(|c| c.chr).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs() {
+        let s = String::from_std("a\tbc\td", term::Brush::black_and_white());
+        assert_eq!(s.expand_tabs(4).to_str(), "a   bc  d");
+        assert_eq!(s.column(2, 4), 4);
+        assert_eq!(s.column(5, 4), 8);
+    }
+
+    #[test]
+    fn test_skip_columns() {
+        let s = String::from_std("한글a", term::Brush::black_and_white());
+        assert_eq!(s.width(), 5);
+        assert_eq!(s.clone().skip_columns(1).to_str(), " 글a");
+        assert_eq!(s.skip_columns(2).to_str(), "글a");
     }
 }
@@ -1,8 +1,11 @@
+use std::cmp;
+use buf;
 use hq;
 use term;
 use ui;
-use util::ResultBox;
+use util::{self, ResultBox};
 use ui::comp::{Component, View};
+use ui::line_editor::{LineEditor, LineEditorRes};
 
 #[derive(PartialEq)]
 pub enum Status {
@@ -10,13 +13,28 @@ pub enum Status {
     Notify,
     Navigate,
     Shortcut,
+    Prompt,
 }
 
 #[derive(UiView)]
 pub struct CommandBar {
     view: View,
     status: Status,
-    data: String,
+    /// The single-line input, edited as the editor does.
+    input: buf::Buffer,
+    line_editor: LineEditor,
+    /// The kind of the history of the input, if recorded.
+    history: Option<hq::History>,
+    /// How many entries back in the history the input is; 0 for the typed one.
+    history_pos: usize,
+    /// The input typed before walking the history.
+    typed: String,
+    /// The query of the incremental history search.
+    isearch: Option<String>,
+    /// Completed by Tab.
+    candidates: Vec<String>,
+    /// The keys continuing the key sequence, with the bound commands.
+    hints: Vec<(String, String)>,
     message: String,
     background: term::Brush,
 }
@@ -25,7 +43,14 @@ impl Default for CommandBar {
     fn default() -> CommandBar {
         CommandBar {
             status: Status::Standby,
-            data: String::with_capacity(80),
+            input: CommandBar::new_input(),
+            line_editor: LineEditor::new(),
+            history: Some(hq::History::Command),
+            history_pos: 0,
+            typed: String::new(),
+            isearch: None,
+            candidates: vec![],
+            hints: vec![],
             message: String::with_capacity(80),
             view: Default::default(),
             background: term::Brush::new(
@@ -37,18 +62,244 @@ impl Default for CommandBar {
 }
 
 impl CommandBar {
+    fn new_input() -> buf::Buffer {
+        let mut res: buf::Buffer = Default::default();
+        res.set_auto_indent(false);
+        res
+    }
+
+    /// Clear the input.
+    fn clear(&mut self) {
+        self.input = CommandBar::new_input();
+        self.line_editor = LineEditor::new();
+        self.history_pos = 0;
+        self.typed.clear();
+        self.isearch = None;
+    }
+
+    /// The text of the input.
+    fn data(&mut self) -> String {
+        self.input.lines().concat()
+    }
+
+    /// Replace the input, with the cursor at the end.
+    fn set_data(&mut self, s: &str) {
+        self.input = CommandBar::new_input();
+        for c in s.chars() {
+            self.input.insert(c, 0);
+        }
+    }
+
+    /// Complete the input to the longest common prefix of the matching candidates.
+    fn complete(&mut self) {
+        let data = self.data();
+        let common = {
+            let mut matches = self.candidates.iter().filter(|s| s.starts_with(&data[..]));
+            let first = match matches.next() {
+                Some(s) => s.clone(),
+                None => return,
+            };
+            matches.fold(first, |acc, s| {
+                acc.chars()
+                    .zip(s.chars())
+                    .take_while(|&(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            })
+        };
+        if common.len() > data.len() {
+            self.set_data(&common);
+        }
+    }
+
+    /// Show the entry `pos` steps back in the history; 0 for the typed input.
+    fn show_history(&mut self, list: &[String], pos: usize) {
+        if self.history_pos == 0 {
+            self.typed = self.data();
+        }
+        self.history_pos = pos;
+        let s = if pos == 0 {
+            self.typed.clone()
+        } else {
+            list[list.len() - pos].clone()
+        };
+        self.set_data(&s);
+    }
+
+    /// Show the older or the newer entry of the history.
+    fn walk_history(&mut self, workspace: &mut hq::Workspace, older: bool) {
+        let kind = match self.history {
+            Some(kind) => kind,
+            None => return,
+        };
+        let list = workspace.history().get(kind);
+        if older && self.history_pos < list.len() {
+            let pos = self.history_pos + 1;
+            self.show_history(list, pos);
+        } else if !older && self.history_pos > 0 {
+            let pos = self.history_pos - 1;
+            self.show_history(list, pos);
+        }
+    }
+
+    /// Show the newest entry containing the query, from `from` steps back in the history.
+    fn search_history(&mut self, workspace: &mut hq::Workspace, from: usize) {
+        let (kind, query) = match (self.history, self.isearch.clone()) {
+            (Some(kind), Some(query)) => (kind, query),
+            _ => return,
+        };
+        let list = workspace.history().get(kind);
+        let found = (cmp::max(from, 1)..list.len() + 1).find(|&pos| {
+            list[list.len() - pos].contains(&query[..])
+        });
+        if let Some(pos) = found {
+            self.show_history(list, pos);
+        }
+    }
+
+    /// Handle the operation in the incremental history search.
+    /// Return None if the operation ends the search.
+    fn on_isearch(
+        &mut self,
+        workspace: &mut hq::Workspace,
+        op: ui::Operation,
+    ) -> ResultBox<Option<ui::Response>> {
+        let from = match op {
+            ui::Operation::HistorySearch => self.history_pos + 1,
+            ui::Operation::Insert(c) => {
+                if let Some(ref mut query) = self.isearch {
+                    query.push(c);
+                }
+                self.history_pos
+            }
+            ui::Operation::DeleteBackwardChar => {
+                if let Some(ref mut query) = self.isearch {
+                    query.pop();
+                }
+                return Ok(Some(self.refresh(workspace)?));
+            }
+            _ => {
+                self.isearch = None;
+                return Ok(None);
+            }
+        };
+        self.search_history(workspace, from);
+        Ok(Some(self.refresh(workspace)?))
+    }
+
+    /// Run the operation on the input.
+    fn on_operation(
+        &mut self,
+        workspace: &mut hq::Workspace,
+        op: ui::Operation,
+    ) -> ResultBox<ui::Response> {
+        use self::Status::*;
+        use ui::Operation::*;
+        if self.isearch.is_some() {
+            if let Some(res) = self.on_isearch(workspace, op)? {
+                return Ok(res);
+            }
+        }
+        match op {
+            ExitPrompt => {
+                let data = self.data();
+                if let (Some(kind), false) = (self.history, self.status == Notify) {
+                    // Failing to save the history must not block the command.
+                    workspace.history().add(kind, &data).unwrap_or(());
+                }
+                Ok(ui::Response::Command(data))
+            }
+            _ if self.status == Shortcut => Ok(ui::Response::None),
+            Insert(_) if self.status == Notify => {
+                self.standby();
+                self.line_editor.on_operation(&mut self.input, op)?;
+                self.refresh(workspace)
+            }
+            _ if self.status == Notify => Ok(ui::Response::None),
+            PreviousHistory => {
+                self.walk_history(workspace, true);
+                self.refresh(workspace)
+            }
+            NextHistory => {
+                self.walk_history(workspace, false);
+                self.refresh(workspace)
+            }
+            HistorySearch if self.history.is_some() => {
+                self.isearch = Some(String::new());
+                self.refresh(workspace)
+            }
+            Complete => {
+                self.complete();
+                self.refresh(workspace)
+            }
+            // Keep the input in a line.
+            NextLine | PreviousLine | Newline => Ok(ui::Response::None),
+            _ => {
+                match self.line_editor.on_operation(&mut self.input, op)? {
+                    LineEditorRes::Unhandled => Ok(ui::Response::None),
+                    LineEditorRes::Ui(resp @ ui::Response::Notify(_)) => Ok(resp),
+                    _ => self.refresh(workspace),
+                }
+            }
+        }
+    }
+
+    /// The message before the input.
+    fn prompt(&self) -> String {
+        match self.isearch {
+            Some(ref query) => format!("(history search) '{}': ", query),
+            None => self.message.clone(),
+        }
+    }
+
     /// Notify a given message.
-    fn notify(&mut self, msg: &str) -> ui::Response {
+    fn notify(&mut self, workspace: &mut hq::Workspace, msg: &str) -> ResultBox<ui::Response> {
+        self.message = String::from(msg);
         self.status = Status::Notify;
-        let mut rect = term::Rect::new(self.view.width, self.view.height, self.background);
-        rect.draw_str(msg, 0, 0);
-        ui::Response::Term {
-            refresh: Some(term::Refresh {
-                x: 0,
-                y: 0,
-                rect: rect,
-            }),
-            cursor: None,
+        self.refresh(workspace)
+    }
+
+    /// The width of the prompt message before the input.
+    fn prompt_len(&self) -> usize {
+        if self.isearch.is_some() || self.status == Status::Prompt ||
+            self.status == Status::Navigate
+        {
+            util::str_width(&self.prompt())
+        } else {
+            0
+        }
+    }
+
+    /// The width of a column of the hints, and the number of the columns.
+    fn hint_columns(&self) -> (usize, usize) {
+        let width = self.hints
+            .iter()
+            .map(|&(ref key, ref name)| util::str_width(key) + util::str_width(name) + 4)
+            .max()
+            .unwrap_or(1);
+        (width, cmp::max(self.view.width / width, 1))
+    }
+
+    /// The number of the lines of the hints.
+    fn hint_rows(&self) -> usize {
+        let (_, columns) = self.hint_columns();
+        (self.hints.len() + columns - 1) / columns
+    }
+
+    /// Start typing a command name.
+    pub fn standby(&mut self) {
+        self.status = Status::Standby;
+        self.clear();
+        self.message.clear();
+        self.history = Some(hq::History::Command);
+        self.candidates.clear();
+    }
+
+    /// True iff the command bar is waiting for the input.
+    pub fn takes_input(&self) -> bool {
+        match self.status {
+            Status::Standby | Status::Prompt | Status::Navigate => true,
+            Status::Notify | Status::Shortcut => false,
         }
     }
 
@@ -62,20 +313,38 @@ impl CommandBar {
         c: ui::CommandBar,
         workspace: &mut hq::Workspace,
     ) -> ResultBox<ui::Response> {
+        self.hints.clear();
         match c {
             ui::CommandBar::Navigate(msg) => {
                 // Turn on the navigator
-                self.data.clear();
+                self.clear();
                 self.message = String::from(msg);
+                self.history = Some(hq::History::Path);
+                self.candidates.clear();
                 self.status = Status::Navigate;
                 self.refresh(workspace)
             }
+            ui::CommandBar::Prompt(prompt) => {
+                // Ask the user with the message
+                self.clear();
+                self.message = prompt.message;
+                self.history = prompt.history;
+                self.candidates = prompt.candidates;
+                self.status = Status::Prompt;
+                self.refresh(workspace)
+            }
             ui::CommandBar::Shortcut(s) => {
                 self.message = String::from(s.clone());
                 self.status = Status::Shortcut;
                 self.refresh(workspace)
             }
-            ui::CommandBar::Notify(s) => Ok(self.notify(&s)),
+            ui::CommandBar::Hint(s, hints) => {
+                self.message = s;
+                self.hints = hints;
+                self.status = Status::Shortcut;
+                self.refresh(workspace)
+            }
+            ui::CommandBar::Notify(s) => self.notify(workspace, &s),
         }
     }
 }
@@ -86,6 +355,8 @@ impl Component for CommandBar {
         let height_parent = self.view.height;
         self.view.height = if self.status == Status::Navigate {
             height_parent / 3
+        } else if self.status == Status::Shortcut && !self.hints.is_empty() {
+            cmp::max(cmp::min(self.hint_rows() + 1, height_parent / 2), 1)
         } else {
             1
         };
@@ -93,37 +364,10 @@ impl Component for CommandBar {
         Ok(())
     }
 
-    /// Handle the keyboard input.
+    /// Insert the characters typed. The other keys run commands.
     fn on_key(&mut self, workspace: &mut hq::Workspace, k: term::Key) -> ResultBox<ui::Response> {
         match k {
-            term::Key::CR => Ok(ui::Response::Command(self.data.clone())),
-            term::Key::Char(c) => {
-                use self::Status::*;
-                match self.status {
-                    Standby | Navigate => {
-                        // TODO: Must consider unicode.
-                        let prev = self.data.len();
-                        self.data.push(c);
-                        Ok(ui::Response::Term {
-                            refresh: Some(term::Refresh {
-                                x: prev,
-                                y: 0,
-                                rect: term::Rect::new_from_char(
-                                    term::Char::new(c, self.background),
-                                ),
-                            }),
-                            cursor: Some((self.data.len(), 0)),
-                        })
-                    }
-                    Notify => {
-                        self.status = Status::Standby;
-                        self.data.clear();
-                        self.data.push(c);
-                        self.refresh(workspace)
-                    }
-                    Shortcut => unreachable!(),
-                }
-            }
+            term::Key::Char(c) => self.on_operation(workspace, ui::Operation::Insert(c)),
             _ => Ok(ui::Response::None),
         }
     }
@@ -132,6 +376,7 @@ impl Component for CommandBar {
     fn handle(&mut self, workspace: &mut hq::Workspace, e: ui::Request) -> ResultBox<ui::Response> {
         match e {
             ui::Request::CommandBar(c) => self.handle_command_bar(c, workspace),
+            ui::Request::Operation(op) => self.on_operation(workspace, op),
             _ => Ok(ui::Response::None),
         }
     }
@@ -145,16 +390,168 @@ impl Component for CommandBar {
             }
             res
         } else {
-            term::Rect::new(self.view.width, self.view.height, self.background)
+            let mut res = term::Rect::new(self.view.width, self.view.height, self.background);
+            let (width, columns) = self.hint_columns();
+            for (i, &(ref key, ref name)) in self.hints.iter().enumerate() {
+                let y = i / columns + 1;
+                if y < self.view.height {
+                    res.draw_str(&format!("{} → {}", key, name), i % columns * width, y);
+                }
+            }
+            res
+        };
+        rect.draw_str(&self.prompt(), 0, 0);
+        let cursor = match self.status {
+            Status::Standby | Status::Navigate | Status::Prompt => {
+                let x = self.prompt_len();
+                self.line_editor.resize(0, self.view.width.saturating_sub(x))?;
+                self.line_editor.scroll_to(self.input.x());
+                let line = self.line_editor.render(&mut self.input)?;
+                rect.draw(&term::Rect::new_from_line(line), x, 0);
+                (x + self.line_editor.translate_cursor(self.input.x()), 0)
+            }
+            _ => (0, 0),
         };
-        rect.draw_str(&self.message, 0, 0);
         Ok(ui::Response::Term {
             refresh: Some(term::Refresh {
                 x: 0,
                 y: 0,
                 rect: rect,
             }),
-            cursor: Some((0, 0)),
+            cursor: Some(cursor),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operate(
+        bar: &mut CommandBar,
+        workspace: &mut hq::Workspace,
+        op: ui::Operation,
+    ) -> ui::Response {
+        bar.handle(workspace, ui::Request::Operation(op)).unwrap()
+    }
+
+    #[test]
+    fn test_line_editing() {
+        let mut workspace = hq::Workspace::new().unwrap();
+        // Do not save the history.
+        *workspace.history() = Default::default();
+        let mut bar: CommandBar = Default::default();
+        bar.resize(&mut workspace, 0, 0, 6, 1).unwrap();
+        for c in "abcdefgh".chars() {
+            bar.on_key(&mut workspace, term::Key::Char(c)).unwrap();
+        }
+        match bar.refresh(&mut workspace).unwrap() {
+            ui::Response::Term { cursor: Some((x, _)), .. } => assert!(x < 6),
+            _ => panic!("no cursor"),
+        }
+        let ops = vec![
+            ui::Operation::BeginningOfLine,
+            ui::Operation::Insert('x'),
+            ui::Operation::EndOfLine,
+            ui::Operation::DeleteBackwardChar,
+            ui::Operation::BeginningOfLine,
+            ui::Operation::ForwardChar,
+            ui::Operation::ForwardChar,
+            ui::Operation::KillLine,
+        ];
+        for op in ops {
+            operate(&mut bar, &mut workspace, op);
+        }
+        match operate(&mut bar, &mut workspace, ui::Operation::ExitPrompt) {
+            ui::Response::Command(s) => assert_eq!(s, "xa"),
+            _ => panic!("no command"),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let mut workspace = hq::Workspace::new().unwrap();
+        *workspace.history() = Default::default();
+        for s in &["foo", "bar", "food"] {
+            workspace.history().add(hq::History::Search, s).unwrap();
+        }
+        let mut bar: CommandBar = Default::default();
+        bar.resize(&mut workspace, 0, 0, 40, 1).unwrap();
+        bar.handle_command_bar(
+            ui::CommandBar::Prompt(ui::Prompt {
+                message: String::from("Search: "),
+                history: Some(hq::History::Search),
+                candidates: vec![],
+            }),
+            &mut workspace,
+        ).unwrap();
+        bar.on_key(&mut workspace, term::Key::Char('x')).unwrap();
+        operate(&mut bar, &mut workspace, ui::Operation::PreviousHistory);
+        operate(&mut bar, &mut workspace, ui::Operation::PreviousHistory);
+        assert_eq!(bar.data(), "bar");
+        operate(&mut bar, &mut workspace, ui::Operation::NextHistory);
+        operate(&mut bar, &mut workspace, ui::Operation::NextHistory);
+        assert_eq!(bar.data(), "x");
+        operate(&mut bar, &mut workspace, ui::Operation::HistorySearch);
+        bar.on_key(&mut workspace, term::Key::Char('o')).unwrap();
+        assert_eq!(bar.data(), "food");
+        operate(&mut bar, &mut workspace, ui::Operation::HistorySearch);
+        assert_eq!(bar.data(), "foo");
+        operate(&mut bar, &mut workspace, ui::Operation::EndOfLine);
+        bar.on_key(&mut workspace, term::Key::Char('!')).unwrap();
+        operate(&mut bar, &mut workspace, ui::Operation::ExitPrompt);
+        assert_eq!(
+            workspace.history().get(hq::History::Search),
+            &["foo", "bar", "food", "foo!"]
+        );
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut workspace = hq::Workspace::new().unwrap();
+        *workspace.history() = Default::default();
+        let mut bar: CommandBar = Default::default();
+        bar.resize(&mut workspace, 0, 0, 40, 1).unwrap();
+        bar.handle_command_bar(
+            ui::CommandBar::Prompt(ui::Prompt {
+                message: String::from("Encoding: "),
+                history: None,
+                candidates: vec![
+                    String::from("utf-8"),
+                    String::from("utf-8-bom"),
+                    String::from("utf-16le"),
+                ],
+            }),
+            &mut workspace,
+        ).unwrap();
+        operate(&mut bar, &mut workspace, ui::Operation::Complete);
+        assert_eq!(bar.data(), "utf-");
+        bar.on_key(&mut workspace, term::Key::Char('8')).unwrap();
+        operate(&mut bar, &mut workspace, ui::Operation::Complete);
+        assert_eq!(bar.data(), "utf-8");
+        bar.on_key(&mut workspace, term::Key::Char('-')).unwrap();
+        operate(&mut bar, &mut workspace, ui::Operation::Complete);
+        assert_eq!(bar.data(), "utf-8-bom");
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut workspace = hq::Workspace::new().unwrap();
+        let mut bar: CommandBar = Default::default();
+        let hints = vec![
+            (String::from("C-f"), String::from("find-file")),
+            (String::from("C-s"), String::from("save-buffer")),
+            (String::from("u"), String::from("undo")),
+        ];
+        bar.resize(&mut workspace, 0, 0, 40, 20).unwrap();
+        bar.handle_command_bar(ui::CommandBar::Hint(String::from("C-x-"), hints), &mut workspace)
+            .unwrap();
+        bar.resize(&mut workspace, 0, 0, 40, 20).unwrap();
+        assert_eq!(bar.hint_columns(), (18, 2));
+        assert_eq!(bar.view.height, 3);
+        bar.handle_command_bar(ui::CommandBar::Shortcut(String::from("C-x-")), &mut workspace)
+            .unwrap();
+        bar.resize(&mut workspace, 0, 0, 40, 20).unwrap();
+        assert_eq!(bar.view.height, 1);
+    }
+}
@@ -67,9 +67,10 @@ impl LineCache {
     pub fn refresh_all(&mut self, view: &View, buffer: &mut buf::Buffer) {
         self.lines.clear();
 
+        let brackets = buffer.bracket_highlights();
         let mut line_idx = 0;
         while let Some(_) = buffer.get(line_idx + self.y_offset) {
-            self.refresh(view, buffer, line_idx);
+            self.refresh(view, buffer, line_idx, &brackets);
             line_idx += 1;
             if line_idx > view.height {
                 break;
@@ -85,13 +86,20 @@ impl LineCache {
     ) -> &term::Line {
         let y_offset = self.y_offset;
         let line_idx = linenum - y_offset;
-        self.refresh(view, buffer, line_idx);
+        let brackets = buffer.bracket_highlights();
+        self.refresh(view, buffer, line_idx, &brackets);
         &self.lines[line_idx]
     }
 
     /// Refresh from the buffer.
     /// Return true iff there is the correcsponding line.
-    fn refresh(&mut self, view: &View, buffer: &mut buf::Buffer, line_idx: usize) -> bool {
+    fn refresh(
+        &mut self,
+        view: &View,
+        buffer: &mut buf::Buffer,
+        line_idx: usize,
+        brackets: &[buf::Range],
+    ) -> bool {
         // TODO: Reuse
         while self.lines.len() <= line_idx {
             self.lines.push(term::Line::new_splitted(
@@ -112,11 +120,28 @@ impl LineCache {
             0,
             view.theme.arrow_fg,
         );
-        if let Some(s) = buffer.get(linenum) {
-            self.lines[line_idx].draw_str_ex(s, self.linenum_width, 0, view.theme.arrow_fg);
-            true
-        } else {
-            false
+        let tab_width = buffer.tab_width();
+        let s = match buffer.get(linenum) {
+            Some(s) => s.clone(),
+            None => return false,
+        };
+        self.lines[line_idx].draw_str_ex(
+            &s.expand_tabs(tab_width),
+            self.linenum_width,
+            0,
+            view.theme.arrow_fg,
+        );
+        let highlights = buffer.highlights().iter().map(|r| (r, view.theme.highlight_bg));
+        let brackets = brackets.iter().map(|r| (r, view.theme.bracket_bg));
+        for (range, bg) in highlights.chain(brackets) {
+            if let Some((from, to)) = range.columns(linenum, s.len()) {
+                self.lines[line_idx].highlight(
+                    self.linenum_width + s.column(from, tab_width),
+                    self.linenum_width + s.column(to, tab_width),
+                    bg,
+                );
+            }
         }
+        true
     }
 }
@@ -1,12 +1,13 @@
 mod line_cache;
 mod scrollable;
 
+use std::cmp;
 use buf;
 use hq;
 use term;
 use ui;
 use ui::comp::{Component, View};
-use ui::line_editor::LineEditor;
+use ui::line_editor::{LineEditor, LineEditorRes};
 use util::ResultBox;
 
 use self::scrollable::Scrollable;
@@ -18,6 +19,21 @@ pub struct Editor {
     line_editor: LineEditor,
     buffer_name: String,
     line_cache: LineCache,
+    recenter: Recenter,
+}
+
+/// Where the cursor line goes by the next recenter.
+#[derive(Clone, Copy, PartialEq)]
+enum Recenter {
+    Center,
+    Top,
+    Bottom,
+}
+
+impl Default for Recenter {
+    fn default() -> Recenter {
+        Recenter::Center
+    }
 }
 
 impl Scrollable for Editor {
@@ -131,24 +147,107 @@ impl Editor {
         unreachable!();
     }
 
-    /// Return the buffer of this editor.
-    fn get_buffer<'a>(&self, workspace: &'a mut hq::Workspace) -> ResultBox<&'a mut buf::Buffer> {
-        workspace.buf(&self.buffer_name)
+    /// Move the cursor to the beginning or the end of the buffer.
+    fn on_buffer_edge(&mut self, buffer: &mut buf::Buffer, end: bool) -> ResultBox<ui::Response> {
+        if end {
+            buffer.move_end_of_buffer();
+        } else {
+            buffer.move_begin_of_buffer();
+        }
+        self.scroll(buffer);
+        self.refresh_with_buffer(buffer)
     }
-}
 
-impl Component for Editor {
-    /// Update each of `line_cache`.
-    fn on_resize(&mut self, _: &mut hq::Workspace) -> ResultBox<()> {
-        self.line_editor.resize(0, self.view.width)?;
-        Ok(())
+    /// Scroll by a page with two lines of context, and move the cursor together.
+    fn on_page(&mut self, buffer: &mut buf::Buffer, down: bool) -> ResultBox<ui::Response> {
+        let page = cmp::max(self.view.height, 3) - 2;
+        let (y_offset, y) = (self.y_offset(), buffer.y());
+        if down {
+            let last = buffer.line_num() - 1;
+            self.set_y_offset(cmp::min(y_offset + page, last));
+            buffer.move_to_line(cmp::min(y + page, last));
+        } else {
+            self.set_y_offset(y_offset.saturating_sub(page));
+            buffer.move_to_line(y.saturating_sub(page));
+        }
+        self.scroll(buffer);
+        self.refresh_with_buffer(buffer)
     }
 
-    /// Process keyboard event.
-    fn on_key(&mut self, workspace: &mut hq::Workspace, k: term::Key) -> ResultBox<ui::Response> {
+    /// Scroll so that the cursor line is at the center, the top, and the bottom in turn.
+    fn on_recenter(&mut self, buffer: &mut buf::Buffer) -> ResultBox<ui::Response> {
+        let (y, height) = (buffer.y(), self.view.height);
+        let (y_offset, next) = match self.recenter {
+            Recenter::Center => (y.saturating_sub(height / 2), Recenter::Top),
+            Recenter::Top => (y, Recenter::Bottom),
+            Recenter::Bottom => (y.saturating_sub(height), Recenter::Center),
+        };
+        self.set_y_offset(y_offset);
+        self.recenter = next;
+        self.refresh_with_buffer(buffer)
+    }
+
+    /// Run the operation given the prefix argument: repeat it n times,
+    /// except that kill-line kills n whole lines.
+    fn on_repeat(
+        &mut self,
+        buffer: &mut buf::Buffer,
+        op: ui::Operation,
+        n: usize,
+    ) -> ResultBox<ui::Response> {
+        let kill = op == ui::Operation::KillLine;
+        let times = if kill { 1 } else { n };
+        for _ in 0..times {
+            let res = if kill {
+                self.line_editor.on_kill_lines(buffer, n)?
+            } else {
+                self.line_editor.on_operation(buffer, op)?
+            };
+            match res {
+                LineEditorRes::Ui(resp @ ui::Response::Notify(_)) => return Ok(resp),
+                LineEditorRes::Unhandled => return Ok(ui::Response::None),
+                _ => (),
+            }
+        }
+        self.set_linenum_max(buffer.line_num());
+        self.scroll(buffer);
+        self.refresh_with_buffer(buffer)
+    }
+
+    /// Run the operation on the buffer.
+    fn on_operation(
+        &mut self,
+        workspace: &mut hq::Workspace,
+        op: ui::Operation,
+    ) -> ResultBox<ui::Response> {
         use ui::line_editor::LineEditorRes::*;
+        let count = workspace.prefix();
         let buffer = self.get_buffer(workspace)?;
-        match self.line_editor.on_key(buffer, k)? {
+        if op != ui::Operation::Recenter {
+            self.recenter = Recenter::Center;
+        }
+        match op {
+            ui::Operation::BeginningOfBuffer => return self.on_buffer_edge(buffer, false),
+            ui::Operation::EndOfBuffer => return self.on_buffer_edge(buffer, true),
+            ui::Operation::ScrollUp => return self.on_page(buffer, true),
+            ui::Operation::ScrollDown => return self.on_page(buffer, false),
+            ui::Operation::Recenter => return self.on_recenter(buffer),
+            _ => (),
+        }
+        if let Some(n) = count {
+            return self.on_repeat(buffer, op, n);
+        }
+        let highlighted = !buffer.highlights().is_empty();
+        let brackets = buffer.bracket_highlights();
+        let res = self.line_editor.on_operation(buffer, op)?;
+        if highlighted && buffer.highlights().is_empty() ||
+            brackets != buffer.bracket_highlights()
+        {
+            // Clear the highlights drawn on other lines.
+            self.set_linenum_max(buffer.line_num());
+            return self.refresh_with_buffer(buffer);
+        }
+        match res {
             Ui(resp) => {
                 // TODO: Do something
                 let y = buffer.y();
@@ -165,12 +264,52 @@ impl Component for Editor {
         }
     }
 
+    /// The title of the editor: the buffer name with its flags.
+    pub fn title(&self, workspace: &mut hq::Workspace) -> ResultBox<String> {
+        let mut res = self.buffer_name.clone();
+        let buffer = self.get_buffer(workspace)?;
+        if buffer.modified() {
+            res.push_str(" *");
+        }
+        if buffer.read_only() {
+            res.push_str(" [RO]");
+        }
+        let format = buffer.format();
+        if format.encoding != buf::Encoding::Utf8 || format.line_ending != buf::LineEnding::Lf {
+            res.push_str(&format!(" [{}]", format));
+        }
+        Ok(res)
+    }
+
+    /// Return the buffer of this editor.
+    fn get_buffer<'a>(&self, workspace: &'a mut hq::Workspace) -> ResultBox<&'a mut buf::Buffer> {
+        workspace.buf(&self.buffer_name)
+    }
+}
+
+impl Component for Editor {
+    /// Update each of `line_cache`.
+    fn on_resize(&mut self, _: &mut hq::Workspace) -> ResultBox<()> {
+        self.line_editor.resize(0, self.view.width)?;
+        Ok(())
+    }
+
+    /// Insert the characters typed. The other keys run commands.
+    fn on_key(&mut self, workspace: &mut hq::Workspace, k: term::Key) -> ResultBox<ui::Response> {
+        match k {
+            term::Key::Char(c) => self.on_operation(workspace, ui::Operation::Insert(c)),
+            _ => Ok(ui::Response::Unhandled),
+        }
+    }
+
     /// Refresh the editor.
     fn refresh(&mut self, workspace: &mut hq::Workspace) -> ResultBox<ui::Response> {
         self.line_editor.set_linenum_width(
             self.line_cache.linenum_width(),
         );
         let buffer = self.get_buffer(workspace)?;
+        self.set_linenum_max(buffer.line_num());
+        self.scroll(buffer);
         self.refresh_with_buffer(buffer)
     }
 
@@ -182,11 +321,28 @@ impl Component for Editor {
     ) -> ResultBox<ui::Response> {
         match e {
             ::ui::Request::OpenBuffer(s) => {
-                self.buffer_name = s;
+                workspace.set_current(&s);
+                let opened = self.buffer_name != s;
+                if opened {
+                    self.buffer_name = s;
+                    self.set_y_offset(0);
+                }
                 let buffer = self.get_buffer(workspace)?;
                 self.set_linenum_max(buffer.line_num());
-                Ok(ui::Response::None)
+                // The cursor may be placed anywhere, e.g. by `+LINE:COL`.
+                self.scroll(buffer);
+                if opened && buffer.has_recovery() {
+                    Ok(ui::Response::Notify(format!(
+                        "{} has auto-save data; use recover-file to restore it.",
+                        self.buffer_name
+                    )))
+                } else if opened && buffer.is_new_file() {
+                    Ok(ui::Response::Notify(String::from("(New file)")))
+                } else {
+                    Ok(ui::Response::None)
+                }
             }
+            ::ui::Request::Operation(op) => self.on_operation(workspace, op),
             _ => Ok(ui::Response::Unhandled),
         }
     }
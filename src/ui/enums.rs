@@ -1,3 +1,4 @@
+use hq;
 use term;
 
 #[derive(Clone, Debug)]
@@ -5,14 +6,68 @@ pub enum CommandBar {
     Notify(String),
     Navigate(String),
     Shortcut(String),
+    /// The key sequence being typed, with the keys continuing it and the bound commands.
+    Hint(String, Vec<(String, String)>),
+    Prompt(Prompt),
+}
+
+/// A question asked in the command bar.
+#[derive(Clone, Debug, Default)]
+pub struct Prompt {
+    pub message: String,
+    /// Record the input in the history if any.
+    pub history: Option<hq::History>,
+    /// Completed by Tab.
+    pub candidates: Vec<String>,
+}
+
+/// An operation of a command, run by the focused component on its input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// Insert the character typed.
+    Insert(char),
+    BeginningOfLine,
+    EndOfLine,
+    Newline,
+    DeleteBackwardChar,
+    KillLine,
+    NextLine,
+    PreviousLine,
+    ForwardChar,
+    BackwardChar,
+    ForwardWord,
+    BackwardWord,
+    KillWord,
+    BackwardKillWord,
+    TransposeChars,
+    TransposeWords,
+    UpcaseWord,
+    DowncaseWord,
+    CapitalizeWord,
+    IndentForTab,
+    BeginningOfBuffer,
+    EndOfBuffer,
+    ScrollUp,
+    ScrollDown,
+    Recenter,
+    ToggleSplit,
+    ExecuteCommand,
+    ExitPrompt,
+    PreviousHistory,
+    NextHistory,
+    HistorySearch,
+    Complete,
 }
 
 #[derive(Clone, Debug)]
 pub enum Request {
     OpenBuffer(String),
+    // Run the command next.
+    Command(String),
     CommandBar(CommandBar),
     // From hq.
     Keyboard(term::Key),
+    Operation(Operation),
     Resize(usize, usize),
     Single(usize),
     Pair(usize, usize),
@@ -22,6 +77,7 @@ pub enum Request {
 #[derive(Debug)]
 pub enum Response {
     Command(String),
+    Notify(String),
     Unhandled,
     Quit,
     Term {
@@ -2,7 +2,7 @@ use hq;
 use util::ResultBox;
 use term;
 use ui;
-use ui::comp::{View, Parent, Component};
+use ui::comp::{View, ViewT, Parent, Component};
 use ui::editor::Editor;
 
 #[derive(Default, UiView)]
@@ -27,11 +27,19 @@ impl Component for HSplit {
     }
 
     fn refresh(&mut self, workspace: &mut hq::Workspace) -> ResultBox<ui::Response> {
-        let rect = term::Rect::new(
+        let mut rect = term::Rect::new(
             self.view.width,
             self.view.height,
             term::Brush::new(term::Color::new(0, 0, 0), term::Color::new(200, 250, 250)),
         );
+        // Titles on the top border.
+        for editor in &self.editors {
+            let view = editor.get_view();
+            let title = editor.title(workspace)?;
+            if let Some(line) = rect.lines.get_mut(0) {
+                line.draw_str(&title, view.x, view.width);
+            }
+        }
         self.refresh_children(rect, workspace)
     }
 
@@ -44,10 +52,10 @@ impl Component for HSplit {
         self.editors[self.focused].propagate(e, workspace)
     }
 
-    /// Handle the keyboard event.
-    fn on_key(&mut self, workspace: &mut hq::Workspace, k: term::Key) -> ResultBox<ui::Response> {
-        match k {
-            term::Key::Ctrl('d') => {
+    /// Handle the operations on the windows.
+    fn handle(&mut self, workspace: &mut hq::Workspace, e: ui::Request) -> ResultBox<ui::Response> {
+        match e {
+            ui::Request::Operation(ui::Operation::ToggleSplit) => {
                 self.toggle_split(workspace)?;
                 self.refresh(workspace)
             }
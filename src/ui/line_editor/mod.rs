@@ -1,10 +1,11 @@
 mod movable;
 
+use std::cmp;
 use ui;
-use buf::Buffer;
+use buf::{self, Buffer};
 use term;
 use ui::comp::View;
-use util::ResultBox;
+use util::{self, ResultBox};
 use self::movable::{Movable, Direction};
 
 #[allow(dead_code)]
@@ -14,6 +15,15 @@ pub struct LineEditor {
     linenum_width: usize,
     x_offset: usize,
     more_right: bool,
+    last_edit: Option<Edit>,
+}
+
+/// The kind of the edit, to group consecutive edits into one undo unit.
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    Insert,
+    Delete,
+    Other,
 }
 
 pub enum LineEditorRes {
@@ -34,14 +44,6 @@ impl Movable for LineEditor {
         self.x_offset = value;
     }
 
-    fn increase_x_offset(&mut self, amount: usize) {
-        self.x_offset += amount;
-    }
-
-    fn decrease_x_offset(&mut self, amount: usize) {
-        self.x_offset -= amount;
-    }
-
     fn width(&self) -> usize {
         self.view.width - self.linenum_width - if self.more_right { 1 } else { 0 }
     }
@@ -80,11 +82,13 @@ impl LineEditor {
             self.view.theme.editor_cur(),
             self.linenum_width,
         );
-        cache.draw_str(
-            &format!("{:width$}", buf.y(), width = self.linenum_width),
-            0,
-            0,
-        );
+        if self.linenum_width > 0 {
+            cache.draw_str(
+                &format!("{:width$}", buf.y(), width = self.linenum_width),
+                0,
+                0,
+            );
+        }
         let margin = if self.x_offset > 0 {
             // TODO: Remove me.
             cache.draw_str_ex(&self.arrow_str(), self.linenum_width, 0, color_arrow);
@@ -92,17 +96,44 @@ impl LineEditor {
         } else {
             0
         };
+        let tab_width = buf.tab_width();
+        let line = buf.cur_mut().as_string().clone();
         self.more_right = cache.draw_str_ex(
-            &buf.cur_mut().as_string().clone().skip_n(
-                self.x_offset + margin,
-            ),
+            &line.expand_tabs(tab_width).skip_columns(self.x_offset + margin),
             self.linenum_width + margin,
             0,
             color_arrow,
         );
+        let y = buf.y();
+        let skip = self.x_offset + margin;
+        let brackets = buf.bracket_highlights();
+        let highlights = buf.highlights().iter().map(|r| (r, self.view.theme.highlight_bg));
+        let brackets = brackets.iter().map(|r| (r, self.view.theme.bracket_bg));
+        for (range, bg) in highlights.chain(brackets) {
+            if let Some((from, to)) = range.columns(y, line.len()) {
+                let (from, to) = (line.column(from, tab_width), line.column(to, tab_width));
+                if to > skip {
+                    cache.highlight(
+                        self.linenum_width + margin + from.max(skip) - skip,
+                        self.linenum_width + margin + to - skip,
+                        bg,
+                    );
+                }
+            }
+        }
         Ok(cache)
     }
 
+    /// Scroll horizontally so that the cursor is visible.
+    pub fn scroll_to(&mut self, cursor: usize) {
+        let width = cmp::max(self.width(), 1);
+        if cursor < self.x_offset {
+            self.x_offset = cursor;
+        } else if cursor >= self.x_offset + width {
+            self.x_offset = cursor + 1 - width;
+        }
+    }
+
     /// Calculate the screen's coordinate of the cursor.
     #[inline]
     pub fn translate_cursor(&self, cursor: usize) -> usize {
@@ -118,9 +149,32 @@ impl LineEditor {
         self.view.width - self.linenum_width - cursor
     }
 
+    /// True iff the character does not take exactly one column:
+    /// a tab, a wide or a combining character.
+    #[inline]
+    fn irregular(c: char) -> bool {
+        c == '\t' || util::term_width(c) != 1
+    }
+
+    /// True iff the current line cannot be redrawn partially, one column per character.
+    fn has_irregular(buf: &mut Buffer) -> bool {
+        buf.cur_mut().as_string().iter().any(|c| LineEditor::irregular(c.chr))
+    }
+
     /// Delete the current character.
     fn on_delete(&mut self, buf: &mut Buffer) -> ResultBox<LineEditorRes> {
         use buf::BackspaceRes::{Normal, PrevLine};
+        if LineEditor::has_irregular(buf) {
+            return match buf.backspace(0) {
+                Normal(_) => {
+                    let cursor = buf.x();
+                    self.adjust_x_offset(cursor);
+                    Ok(LineEditorRes::Refresh)
+                }
+                PrevLine(_) => self.on_edited(buf),
+                _ => Ok(LineEditorRes::Unhandled),
+            };
+        }
         let cursor = buf.x();
         match buf.backspace(self.spaces_after_cursor(cursor)) {
             Normal(mut after_cursor) => {
@@ -147,6 +201,17 @@ impl LineEditor {
         }
     }
 
+    /// Kill n whole lines from the cursor.
+    pub fn on_kill_lines(&mut self, buf: &mut Buffer, n: usize) -> ResultBox<LineEditorRes> {
+        if buf.read_only() {
+            return self.response_ui(ui::Response::Notify(String::from("Buffer is read-only.")));
+        }
+        buf.checkpoint();
+        self.last_edit = Some(Edit::Other);
+        buf.kill_lines(n);
+        self.on_edited(buf)
+    }
+
     /// Update the y and width.
     pub fn resize(&mut self, y: usize, width: usize) -> ResultBox<()> {
         self.view.y = y;
@@ -180,13 +245,18 @@ impl LineEditor {
 
     /// Accept the char input.
     pub fn on_char(&mut self, buf: &mut Buffer, c: char) -> ResultBox<LineEditorRes> {
-        if self.view.width < buf.x() + 2 {
-            //panic!("{} {}", self.view.width, buf.x());
+        if LineEditor::irregular(c) || LineEditor::has_irregular(buf) {
+            buf.insert(c, 0);
             let cursor = buf.x();
-            // TODO: ??
-            let after_cursor = term::String::new();
-            let line = term::Line::new_from_string(after_cursor);
-            self.response_cursor_with_line(cursor, line, false)
+            self.adjust_x_offset(cursor);
+            return Ok(LineEditorRes::Refresh);
+        }
+        if buf.x() + 2 > self.x_offset + self.width() {
+            // Scroll to keep the cursor visible.
+            buf.insert(c, 0);
+            let cursor = buf.x();
+            self.scroll_to(cursor);
+            Ok(LineEditorRes::Refresh)
         } else {
             let mut after_cursor = term::String::new();
             after_cursor.push(term::Char::new(c, term::Brush::black_and_white()));
@@ -198,28 +268,122 @@ impl LineEditor {
         }
     }
 
-    /// Move cursor left and right, or Type a character.
-    pub fn on_key(&mut self, buf: &mut Buffer, k: term::Key) -> ResultBox<LineEditorRes> {
-        match k {
-            term::Key::Ctrl('a') |
-            term::Key::Home => self.on_home_end(buf, true),
-            term::Key::Ctrl('e') |
-            term::Key::End => self.on_home_end(buf, false),
-            term::Key::CR => {
-                let cursor = buf.break_line();
+    /// Respond to the cursor moved by a command.
+    fn on_jump(&mut self, buf: &mut Buffer, cursor_prev: term::Cursor) -> ResultBox<LineEditorRes> {
+        let cursor = buf.cursor();
+        if self.adjust_x_offset(cursor.0) || cursor.1 != cursor_prev.1 {
+            Ok(LineEditorRes::Refresh)
+        } else {
+            self.response_cursor(cursor.0)
+        }
+    }
+
+    /// Respond to the buffer edited by a command, which may join lines.
+    fn on_edited(&mut self, buf: &mut Buffer) -> ResultBox<LineEditorRes> {
+        let cursor = buf.x();
+        self.adjust_x_offset(cursor);
+        Ok(LineEditorRes::PullUp)
+    }
+
+    /// The kind of the edit by the operation, or None if it does not modify the buffer.
+    fn edit_kind(op: ui::Operation) -> Option<Edit> {
+        use ui::Operation::*;
+        match op {
+            Insert(_) | IndentForTab => Some(Edit::Insert),
+            DeleteBackwardChar => Some(Edit::Delete),
+            Newline | KillLine | TransposeChars | KillWord | BackwardKillWord | TransposeWords |
+            UpcaseWord | DowncaseWord | CapitalizeWord => Some(Edit::Other),
+            _ => None,
+        }
+    }
+
+    /// Move the cursor, or edit the buffer.
+    pub fn on_operation(
+        &mut self,
+        buf: &mut Buffer,
+        op: ui::Operation,
+    ) -> ResultBox<LineEditorRes> {
+        use ui::Operation::*;
+        let edit = LineEditor::edit_kind(op);
+        if edit.is_some() && buf.read_only() {
+            return self.response_ui(ui::Response::Notify(String::from("Buffer is read-only.")));
+        }
+        if edit.is_some() && (edit == Some(Edit::Other) || edit != self.last_edit) {
+            buf.checkpoint();
+        }
+        self.last_edit = edit;
+        match op {
+            BeginningOfLine => self.on_home_end(buf, true),
+            EndOfLine => self.on_home_end(buf, false),
+            Newline => {
+                let cursor = if buf.auto_indent() {
+                    buf.newline_and_indent()
+                } else {
+                    buf.break_line()
+                };
+                self.adjust_x_offset(cursor.0);
                 Ok(LineEditorRes::LineBreak(cursor))
             }
-            term::Key::Del => self.on_delete(buf),
-            term::Key::Ctrl('k') => self.on_kill_line(buf),
-            term::Key::Ctrl('n') |
-            term::Key::Down => self.on_move(buf, Direction::Vertical(1)),
-            term::Key::Ctrl('p') |
-            term::Key::Up => self.on_move(buf, Direction::Vertical(-1)),
-            term::Key::Ctrl('f') |
-            term::Key::Right => self.on_move(buf, Direction::Horizontal(1)),
-            term::Key::Ctrl('b') |
-            term::Key::Left => self.on_move(buf, Direction::Horizontal(-1)),
-            term::Key::Char(c) => self.on_char(buf, c),
+            DeleteBackwardChar => self.on_delete(buf),
+            KillLine => self.on_kill_line(buf),
+            NextLine => self.on_move(buf, Direction::Vertical(1)),
+            PreviousLine => self.on_move(buf, Direction::Vertical(-1)),
+            ForwardChar => self.on_move(buf, Direction::Horizontal(1)),
+            BackwardChar => self.on_move(buf, Direction::Horizontal(-1)),
+            ForwardWord => {
+                let cursor_prev = buf.cursor();
+                buf.forward_word();
+                self.on_jump(buf, cursor_prev)
+            }
+            BackwardWord => {
+                let cursor_prev = buf.cursor();
+                buf.backward_word();
+                self.on_jump(buf, cursor_prev)
+            }
+            KillWord => {
+                buf.kill_word(true);
+                self.on_edited(buf)
+            }
+            BackwardKillWord => {
+                buf.kill_word(false);
+                self.on_edited(buf)
+            }
+            TransposeChars => {
+                buf.transpose_chars();
+                self.on_edited(buf)
+            }
+            TransposeWords => {
+                buf.transpose_words();
+                self.on_edited(buf)
+            }
+            UpcaseWord => {
+                buf.convert_word(|s| s.to_uppercase());
+                self.on_edited(buf)
+            }
+            DowncaseWord => {
+                buf.convert_word(|s| s.to_lowercase());
+                self.on_edited(buf)
+            }
+            CapitalizeWord => {
+                buf.convert_word(buf::capitalize);
+                self.on_edited(buf)
+            }
+            IndentForTab => {
+                buf.insert_tab();
+                let cursor = buf.x();
+                self.adjust_x_offset(cursor);
+                Ok(LineEditorRes::Refresh)
+            }
+            Insert(c) => {
+                let res = self.on_char(buf, c)?;
+                if buf.dedent_closing() {
+                    let cursor = buf.x();
+                    self.adjust_x_offset(cursor);
+                    Ok(LineEditorRes::Refresh)
+                } else {
+                    Ok(res)
+                }
+            }
             _ => Ok(LineEditorRes::Unhandled),
         }
     }
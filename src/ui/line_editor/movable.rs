@@ -26,8 +26,6 @@ impl Direction {
 pub trait Movable {
     fn x_offset(&self) -> usize;
     fn set_x_offset(&mut self, usize);
-    fn increase_x_offset(&mut self, amount: usize);
-    fn decrease_x_offset(&mut self, amount: usize);
 
     /// The width of the object
     fn width(&self) -> usize;
@@ -53,12 +51,13 @@ pub trait Movable {
         let cursor_prev = buf.cursor();
         let cursor = buf.move_cursor(direction.dx(), direction.dy());
         if cursor_prev.1 == cursor.1 {
-            // Move only in here.
-            if cursor.0 - self.x_offset() >= self.width() {
-                self.increase_x_offset(1);
+            // Move only in here. A wide character may move more than a column.
+            if cursor.0 >= self.x_offset() + self.width() {
+                let x_offset = cursor.0 + 1 - self.width();
+                self.set_x_offset(x_offset);
                 Ok(LineEditorRes::Refresh)
-            } else if self.x_offset() > 0 && cursor.0 - self.x_offset() <= 1 {
-                self.decrease_x_offset(1);
+            } else if self.x_offset() > 0 && cursor.0 <= self.x_offset() + 1 {
+                self.set_x_offset(cursor.0.saturating_sub(1));
                 Ok(LineEditorRes::Refresh)
             } else {
                 self.response_cursor(cursor.0)
@@ -10,4 +10,4 @@ mod command_bar;
 pub use ui::comp::Component;
 pub use ui::screen::Screen;
 pub use ui::theme::Theme;
-pub use ui::enums::{CommandBar, Request, Response};
+pub use ui::enums::{CommandBar, Operation, Prompt, Request, Response};
@@ -15,6 +15,8 @@ pub struct Screen {
     view: View,
     hsplit: UiChild,
     command_bar: UiChild,
+    /// True iff the command bar has been closed and the whole screen must be drawn.
+    stale: bool,
 }
 
 def_child!(UiChild <- HSplit, CommandBar);
@@ -33,6 +35,7 @@ impl Component for Screen {
     }
 
     fn refresh(&mut self, workspace: &mut hq::Workspace) -> ResultBox<ui::Response> {
+        self.stale = false;
         let rect = term::Rect::new(
             self.view.width,
             self.view.height,
@@ -48,17 +51,20 @@ impl Component for Screen {
         e: ui::Request,
     ) -> ResultBox<ui::Response> {
         if self.command_bar().focus() {
-            self.command_bar.propagate(e, workspace)
-        } else {
-            self.hsplit.propagate(e, workspace)
+            let res = self.command_bar.propagate(e, workspace)?;
+            if let ui::Response::Command(_) = res {
+                // The input is done, and the command runs on the editor.
+                self.deactivate_command_bar();
+            }
+            return Ok(res);
         }
-    }
-
-    /// Handle keyboard events.
-    fn on_key(&mut self, workspace: &mut hq::Workspace, k: term::Key) -> ResultBox<ui::Response> {
-        match k {
-            term::Key::Ctrl('c') => self.activate_command_bar(workspace),
-            _ => Ok(ui::Response::Unhandled),
+        let res = self.hsplit.propagate(e, workspace)?;
+        match res {
+            ui::Response::Term { .. } if self.stale => {
+                self.on_resize(workspace)?;
+                self.refresh(workspace)
+            }
+            _ => Ok(res),
         }
     }
 
@@ -66,6 +72,14 @@ impl Component for Screen {
     fn handle(&mut self, workspace: &mut hq::Workspace, e: ui::Request) -> ResultBox<ui::Response> {
         use ui::Request::*;
         match e {
+            e @ CommandBar(ui::CommandBar::Notify(_)) => {
+                // Show the message, and give the focus back to the editor, e.g. on C-g.
+                self.command_bar_mut().set_focus(false);
+                self.hsplit.set_focus(true);
+                self.command_bar.propagate(e, workspace)?;
+                self.on_resize(workspace)?;
+                self.refresh(workspace)
+            }
             e @ CommandBar(_) => {
                 self.activate_command_bar(workspace)?;
                 self.command_bar.propagate(e, workspace)?;
@@ -82,7 +96,11 @@ impl Component for Screen {
                     self.command_bar_mut().set_focus(false);
                     self.hsplit.set_focus(true);
                     self.on_resize(workspace)?;
-                    self.hsplit.propagate(e, workspace)?;
+                    let res = self.hsplit.propagate(e, workspace)?;
+                    if let ui::Response::Notify(_) = res {
+                        // The notification refreshes the screen.
+                        return Ok(res);
+                    }
                     self.refresh(workspace)
                 } else {
                     // Before initialize
@@ -90,6 +108,19 @@ impl Component for Screen {
                     Ok(ui::Response::None)
                 }
             }
+            Operation(ui::Operation::ExecuteCommand) => {
+                // Keep the input being asked for.
+                if !self.command_bar().focus() || !self.command_bar().takes_input() {
+                    self.command_bar_mut().standby();
+                }
+                self.activate_command_bar(workspace)
+            }
+            Operation(_) if self.command_bar().focus() && !self.command_bar().takes_input() => {
+                // Close the key sequence shown, and run on the editor.
+                self.deactivate_command_bar();
+                Ok(ui::Response::Unhandled)
+            }
+            Command(s) => Ok(ui::Response::Command(s)),
             Quit => Ok(ui::Response::Quit),
             _ => Ok(ui::Response::Unhandled),
         }
@@ -115,6 +146,12 @@ impl Screen {
         }
     }
 
+    /// True iff the command bar takes the keys.
+    #[inline]
+    pub fn command_bar_focused(&self) -> bool {
+        self.command_bar().focus()
+    }
+
     /// Resize the command bar; the bottom-side of the ui.
     #[inline]
     fn resize_command_bar(&mut self, workspace: &mut hq::Workspace) -> ResultBox<()> {
@@ -127,6 +164,13 @@ impl Screen {
         )
     }
 
+    /// Give the focus back to the editor. The screen is drawn on the next response.
+    fn deactivate_command_bar(&mut self) {
+        self.command_bar_mut().set_focus(false);
+        self.hsplit.set_focus(true);
+        self.stale = true;
+    }
+
     /// Activate command bar, and redraw the corresponding area.
     #[inline]
     pub fn activate_command_bar(
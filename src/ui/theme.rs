@@ -4,6 +4,8 @@ pub struct Theme {
     pub editor: term::Brush,
     pub linenum: term::Brush,
     pub arrow_fg: term::Color,
+    pub highlight_bg: term::Color,
+    pub bracket_bg: term::Color,
     editor_cur_bg: term::Color,
     linenum_cur_bg: term::Color,
 }
@@ -38,6 +40,8 @@ impl Default for Theme {
                 term::Color::new(80, 80, 80),
             ),
             arrow_fg: term::Color::new(255, 127, 127),
+            highlight_bg: term::Color::new(120, 90, 30),
+            bracket_bg: term::Color::new(60, 110, 120),
             editor_cur_bg: term::Color::new(80, 80, 90),
             linenum_cur_bg: term::Color::new(100, 100, 110),
         }
@@ -1,14 +1,52 @@
 #[macro_use]
 mod macros;
 
+use std::env;
+use std::path::PathBuf;
+use unicode_width::UnicodeWidthChar;
+
 pub type ResultBox<T> = ::std::result::Result<T, Box<::std::error::Error>>;
 
-extern "C" {
-    #[inline]
-    fn wcwidth(chr: u32) -> u32;
-}
+pub const DEFAULT_TAB_WIDTH: usize = 8;
 
+/// The width of the character on the terminal, independent of the locale.
+/// Control and combining characters have no width.
 #[inline]
 pub fn term_width(c: char) -> usize {
-    unsafe { wcwidth(c as u32) as usize }
+    c.width().unwrap_or(0)
+}
+
+/// The width of the string on the terminal.
+#[inline]
+pub fn str_width(s: &str) -> usize {
+    s.chars().map(term_width).sum()
+}
+
+/// The column after the character at the column `x`. A tab goes to the next tab stop.
+#[inline]
+pub fn advance(x: usize, c: char, tab_width: usize) -> usize {
+    if c == '\t' {
+        x + tab_width - x % tab_width
+    } else {
+        x + term_width(c)
+    }
+}
+
+/// The directory of the configuration: `$XDG_CONFIG_HOME/thief` or `~/.config/thief`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("thief"))
+}
+
+/// The directory of the state kept across sessions:
+/// `$XDG_STATE_HOME/thief` or `~/.local/state/thief`.
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local").join("state"),
+    };
+    Some(base.join("thief"))
 }